@@ -14,6 +14,38 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("parse address", |b| {
         b.iter(|| get_addr(black_box(example.to_string())))
     });
+
+    // Run with and without `--features simd-base64` to compare key decoding.
+    let examples: Vec<String> = (0..1000)
+        .map(|i| {
+            format!(
+                "net:10.0.{}.{}:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+                i / 256,
+                i % 256
+            )
+        })
+        .collect();
+    c.bench_function("parse 1000 addresses", |b| {
+        b.iter(|| {
+            for example in &examples {
+                black_box(MultiserverAddress::from_str(example).unwrap());
+            }
+        })
+    });
+    c.bench_function("parse 1000 addresses without keys", |b| {
+        b.iter(|| {
+            for example in &examples {
+                black_box(MultiserverAddress::parse_without_key(example).unwrap());
+            }
+        })
+    });
+    c.bench_function("validate 1000 addresses", |b| {
+        b.iter(|| {
+            for example in &examples {
+                black_box(MultiserverAddress::is_valid(example));
+            }
+        })
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,7 @@
+//! The parser's C ABI, and with the `wasm` feature its wasm-bindgen exports,
+//! linked into one library. The bindings live in `multiserver_address_rs`
+//! behind its `ffi` and `wasm` features; this crate only builds them.
+
+pub use multiserver_address_rs::ffi::*;
+#[cfg(feature = "wasm")]
+pub use multiserver_address_rs::wasm::*;
@@ -0,0 +1,11 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate multiserver_address_rs;
+
+use std::str::FromStr;
+use multiserver_address_rs::MultiserverAddress;
+
+fuzz_target!(|addr: MultiserverAddress| {
+    let parsed = MultiserverAddress::from_str(&addr.to_string()).unwrap();
+    assert_eq!(parsed, addr);
+});
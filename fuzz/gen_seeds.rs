@@ -0,0 +1,101 @@
+// Writes a libFuzzer/AFL dictionary and a seed corpus for the parse_address
+// target. Run from the fuzz directory before fuzzing:
+//
+//     cargo run --bin gen_seeds
+//     cargo fuzz run parse_address -- -dict=parse_address.dict
+extern crate multiserver_address_rs_grammar;
+
+use multiserver_address_rs_grammar::{LAYERS, SEPARATORS, TRANSPORTS};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+const KEY: &str = "HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+
+// Literals the grammar matches inside protocol data. Transport and layer
+// names and the separators come from the grammar crate itself.
+const LITERALS: &[&str] = &[
+    ".ed25519",
+    "@",
+    "/",
+    "=",
+    "[",
+    "]",
+    "sha256-",
+    ".onion",
+    ".i2p",
+    ".b32.i2p",
+    "localhost",
+    "::",
+];
+
+const HOSTS: &[&str] = &[
+    "192.168.178.17",
+    "127.0.0.1",
+    "1200:0000:AB00:1234:0000:2552:7777:1313",
+    "fe80:0:0:0:202:b3ff:fe1e:8329",
+    "fc12:3456:789a:bcde:f012:3456:789a:bcde",
+    "host.com",
+    "localhost",
+    "3wmnbu4vxpgzfvhn.onion",
+    "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion",
+];
+
+// Addresses used by the crate's own unit tests.
+const VECTORS: &[&str] = &[
+    "https:room.example.com:443/alias~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+    "net:host.com:8008:sha256-AbCd~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+    "net:192.168.178.17:99999~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+    "webrtc:signal.example.com~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+    "unix:/tmp/a!:b!!c~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+    "net:host.com:8008~:KEY",
+    "not an address",
+];
+
+fn quote(token: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in token.bytes() {
+        match byte {
+            b'"' | b'\\' => out.push_str(&format!("\\x{:02x}", byte)),
+            0x20..=0x7e => out.push(byte as char),
+            _ => out.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn seeds() -> Vec<String> {
+    let mut seeds: Vec<String> = VECTORS.iter().map(|s| s.to_string()).collect();
+    for transport in TRANSPORTS {
+        for host in HOSTS {
+            seeds.push(format!("{}:{}:8008", transport, host));
+            seeds.push(format!("{}:{}:8008~shs:{}", transport, host, KEY));
+            seeds.push(format!("{}:{}:8008~noauth", transport, host));
+        }
+    }
+    seeds.push(format!("net:host.com:8008~shs:{};net:host.com:8009", KEY));
+    seeds
+}
+
+fn main() {
+    let mut dict = fs::File::create("parse_address.dict").expect("create dictionary");
+    let layers = LAYERS.iter().map(|layer| format!("~{}", layer));
+    let tokens = TRANSPORTS
+        .iter()
+        .chain(LAYERS)
+        .chain(SEPARATORS)
+        .chain(LITERALS)
+        .map(|token| token.to_string())
+        .chain(layers)
+        .chain(Some(KEY.to_string()));
+    for token in tokens {
+        writeln!(dict, "{}", quote(&token)).expect("write dictionary");
+    }
+
+    let dir = Path::new("corpus").join("parse_address");
+    fs::create_dir_all(&dir).expect("create corpus directory");
+    for (i, seed) in seeds().iter().enumerate() {
+        fs::write(dir.join(format!("seed-{:03}", i)), seed).expect("write seed");
+    }
+}
@@ -0,0 +1,207 @@
+//! The multiserver address grammar and the checks that need no key or host
+//! types. The parser and the `msaddr!` macro both build on this crate, so a
+//! literal the macro accepts at compile time is one `from_str` accepts.
+
+use regex::Regex;
+use std::fmt;
+use std::sync::LazyLock;
+
+/// The regex crate matches in time linear in the input, so bounding the input
+/// length and segment count bounds the work done on untrusted gossip.
+pub const MAX_ADDRESS_LEN: usize = 1024;
+pub const MAX_SEGMENTS: usize = 16;
+
+/// Every transport the grammar accepts, by name.
+pub const TRANSPORTS: &[&str] = &[
+    "net", "udp", "http", "https", "ws", "wss", "onion", "quic", "webrtc", "unix", "bt", "dht",
+    "tunnel",
+];
+
+/// The layers that may follow the transport, by name.
+pub const LAYERS: &[&str] = &["shs", "noauth"];
+
+/// `:` ends a layer's name, `~` a layer and `;` an address. `!` escapes any
+/// of them inside protocol data.
+pub const SEPARATORS: &[&str] = &[":", "~", ";", "!"];
+
+pub static ADDRESS_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:webrtc:(?P<signal>(?:![!:~;]|[^!~;])+)|bt:(?P<bt>[0-9A-Fa-f]{12})|dht:(?P<dht>(?:![!:~;]|[^!~;])+)|tunnel:(?P<portal>@[^:~]+):(?P<target>@[^:~]+)|unix:(?P<socket>(?:![!:~;]|[^!:~;])+)|(?P<transport>net|udp|https|http|wss|ws|onion|quic):(\[(?P<ipv6_bracketed>[0-9A-Fa-f:.]+)\]|(?P<ipv4>\d+.\d+.\d+.\d+)|(?P<ipv6>.+:.+:.+:.+:.+:.+:.+:.+)|(?P<url>[^/~]+)):(?P<port>\d+)(?::(?P<cert_hash>[A-Za-z][^~:/]*))?(?P<path>/[^~]*)?|(?P<web>https|http|wss|ws):(?P<web_host>\[[0-9A-Fa-f:.]+\]|[^/~:\[\]]+)(?P<web_path>/[^~]*)?)(?:~shs:(?P<pub_key>[^~]+=(?:\.[\w-]+)?))?(?P<noauth>~noauth)?$").unwrap()
+});
+
+/// The first limit an input breaks, as found by `check_limits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Length { length: usize },
+    Segments { count: usize },
+    ControlCharacter { offset: usize },
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Length { length } => write!(
+                f,
+                "Address string is {} bytes, limit is {}",
+                length, MAX_ADDRESS_LEN
+            ),
+            Limit::Segments { count } => write!(
+                f,
+                "Address string has {} segments, limit is {}",
+                count, MAX_SEGMENTS
+            ),
+            Limit::ControlCharacter { offset } => {
+                write!(f, "Control character at byte {}", offset)
+            }
+        }
+    }
+}
+
+// Checks run before the regex, so oversized or binary input fails fast.
+pub fn check_limits(st: &str) -> Result<(), Limit> {
+    if st.len() > MAX_ADDRESS_LEN {
+        return Err(Limit::Length { length: st.len() });
+    }
+    let count = st.split('~').count();
+    if count > MAX_SEGMENTS {
+        return Err(Limit::Segments { count });
+    }
+    match st.find(char::is_control) {
+        Some(offset) => Err(Limit::ControlCharacter { offset }),
+        None => Ok(()),
+    }
+}
+
+/// The well-known port of a web transport, by name.
+pub fn default_port(transport: &str) -> Option<u16> {
+    match transport {
+        "ws" | "http" => Some(80),
+        "wss" | "https" => Some(443),
+        _ => None,
+    }
+}
+
+/// Whether addresses on the named transport may carry a request path.
+pub fn allows_path(transport: &str) -> bool {
+    matches!(transport, "http" | "https" | "ws" | "wss")
+}
+
+/// Whether `path` is a URL path that multiserver can carry unescaped: `/`
+/// followed by unreserved characters, `%` escapes and the sub-delimiters,
+/// except `!` and `;`, which multiserver reserves, and `~`, which ends the
+/// layer.
+pub fn is_url_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    if bytes.first() != Some(&b'/') {
+        return false;
+    }
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let escape = bytes.get(i + 1..i + 3);
+                if !escape.is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) {
+                    return false;
+                }
+                i += 3;
+                continue;
+            }
+            b if b.is_ascii_alphanumeric() || b"-._/:@$&'()*+,=".contains(&b) => (),
+            _ => return false,
+        }
+        i += 1;
+    }
+    true
+}
+
+// v3 service names are 56 base32 characters encoding key, checksum and
+// version.
+pub fn is_onion_v3(host: &str) -> bool {
+    let host = host.as_bytes();
+    host.len() == 56 + ".onion".len()
+        && host[56..].eq_ignore_ascii_case(b".onion")
+        && host[..56]
+            .iter()
+            .all(|b| b.is_ascii_alphabetic() || (b'2'..=b'7').contains(b))
+}
+
+/// Whether `host` is a plain ASCII name, the only kind that can be
+/// normalized without IDNA.
+pub fn is_ascii_hostname(host: &str) -> bool {
+    !host.is_empty()
+        && host
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b))
+}
+
+/// Splits the key of an `shs` layer into its base64 data and algorithm.
+/// Bare base64 is an ed25519 key, and the `@` of the sigil form older JS
+/// tools write is dropped.
+pub fn split_key(st: &str) -> (&str, &str) {
+    let st = st.strip_prefix('@').unwrap_or(st);
+    match st.find('.') {
+        Some(dot) => (&st[..dot], &st[dot + 1..]),
+        None => (st, "ed25519"),
+    }
+}
+
+/// Removes the `!` escapes from protocol data.
+pub fn unescape(st: &str) -> String {
+    let mut out = String::with_capacity(st.len());
+    let mut chars = st.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '!' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grammar_limits_ok() {
+        assert_eq!(check_limits("net:host.com:8008"), Ok(()));
+        assert_eq!(
+            check_limits(&"~".repeat(MAX_SEGMENTS)),
+            Err(Limit::Segments {
+                count: MAX_SEGMENTS + 1
+            })
+        );
+        assert_eq!(
+            check_limits("net:a\n"),
+            Err(Limit::ControlCharacter { offset: 5 })
+        );
+    }
+    #[test]
+    fn grammar_names_ok() {
+        for name in TRANSPORTS.iter().chain(LAYERS) {
+            assert!(
+                ADDRESS_RE.as_str().contains(name),
+                "{} is not in the grammar",
+                name
+            );
+        }
+        assert!(ADDRESS_RE.is_match("net:host.com:8008~noauth"));
+    }
+    #[test]
+    fn grammar_url_path_ok() {
+        assert!(is_url_path("/"));
+        assert!(is_url_path("/rooms/a%20b/join@host:1"));
+        assert!(!is_url_path("rooms"));
+        assert!(!is_url_path("/a b"));
+        assert!(!is_url_path("/a%2"));
+        assert!(!is_url_path("/a%zz"));
+        assert!(!is_url_path("/a;b"));
+        assert!(!is_url_path("/a?b#c"));
+        assert!(!is_url_path("/caf\u{e9}"));
+    }
+    #[test]
+    fn grammar_split_key_ok() {
+        assert_eq!(split_key("@abc=.ed25519"), ("abc=", "ed25519"));
+        assert_eq!(split_key("abc="), ("abc=", "ed25519"));
+        assert_eq!(split_key("abc=.gabbygrove"), ("abc=", "gabbygrove"));
+    }
+}
@@ -0,0 +1,82 @@
+#ifndef MSADDR_H
+#define MSADDR_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define MSADDR_OK 0
+
+// A required pointer was null or the input was not UTF-8.
+#define MSADDR_ERR_INVALID_ARGUMENT -1
+
+#define MSADDR_ERR_SYNTAX 1
+
+#define MSADDR_ERR_SEMANTIC 2
+
+#define MSADDR_ERR_LIMIT 3
+
+typedef struct MultiserverAddress MultiserverAddress;
+
+// Parses the NUL-terminated `input` and stores the address in `*out`.
+// Returns `MSADDR_OK` or an `MSADDR_ERR_*` code, leaving `*out` untouched
+// on failure.
+//
+// # Safety
+//
+// `input` must be a valid NUL-terminated string and `out` a valid pointer.
+int msaddr_parse(const char *input, MultiserverAddress **out);
+
+// Releases an address from `msaddr_parse`. Null is ignored.
+//
+// # Safety
+//
+// `address` must come from `msaddr_parse` and not have been freed.
+void msaddr_free(MultiserverAddress *address);
+
+// The transport name, e.g. `net`.
+//
+// # Safety
+//
+// `address` must be a live address from `msaddr_parse`.
+char *msaddr_transport(const MultiserverAddress *address);
+
+// The IP, hostname, socket path or other transport data.
+//
+// # Safety
+//
+// `address` must be a live address from `msaddr_parse`.
+char *msaddr_host(const MultiserverAddress *address);
+
+// The port, or 0 for transports without one.
+//
+// # Safety
+//
+// `address` must be a live address from `msaddr_parse` or null.
+uint16_t msaddr_port(const MultiserverAddress *address);
+
+// The `@…=.ed25519` feed id of the `shs` key, or null without one.
+//
+// # Safety
+//
+// `address` must be a live address from `msaddr_parse`.
+char *msaddr_key(const MultiserverAddress *address);
+
+// The address in its multiserver string form.
+//
+// # Safety
+//
+// `address` must be a live address from `msaddr_parse`.
+char *msaddr_to_string(const MultiserverAddress *address);
+
+// Releases a string returned by one of the accessors. Null is ignored.
+//
+// # Safety
+//
+// `st` must come from this library and not have been freed.
+void msaddr_string_free(char *st);
+
+#endif /* MSADDR_H */
@@ -0,0 +1,203 @@
+use multiserver_address_rs_grammar::{
+    allows_path, check_limits, default_port, is_ascii_hostname, is_onion_v3, is_url_path,
+    split_key, unescape, ADDRESS_RE,
+};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use std::net::IpAddr;
+use syn::{parse_macro_input, LitStr};
+
+/// Parses a multiserver address literal at compile time and expands to the
+/// `MultiserverAddress` it describes, built field by field so nothing is left
+/// to fail at runtime.
+///
+/// ```ignore
+/// let pub_addr = msaddr!("net:ssb.example.com:8008~shs:…=");
+/// ```
+///
+/// Keys must be ed25519, and hostnames plain ASCII, since those are the
+/// forms that can be checked without the parser's own key and IDNA types.
+#[proc_macro]
+pub fn msaddr(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+    match expand(&lit.value()) {
+        Ok(tokens) => tokens.into(),
+        Err(message) => syn::Error::new(lit.span(), message)
+            .to_compile_error()
+            .into(),
+    }
+}
+
+// Runs the same steps as `MultiserverAddress::from_str`, on the same
+// grammar, and quotes the result.
+fn expand(st: &str) -> Result<TokenStream2, String> {
+    check_limits(st).map_err(|limit| limit.to_string())?;
+    let caps = ADDRESS_RE
+        .captures(st)
+        .ok_or_else(|| format!("`{}` is not a multiserver address", st))?;
+    let text = |name| caps.name(name).map(|m| m.as_str());
+
+    let mut security = Vec::new();
+    if let Some(pub_key) = text("pub_key") {
+        let (data, algorithm) = split_key(pub_key);
+        if algorithm != "ed25519" {
+            return Err(format!(
+                "msaddr! only embeds ed25519 keys, not {}",
+                algorithm
+            ));
+        }
+        let pub_key = ed25519_key(data)?;
+        security.push(quote!(SecurityLayer::Shs(#pub_key)));
+    }
+    if text("noauth").is_some() {
+        security.push(quote!(SecurityLayer::Noauth));
+    }
+
+    let mut port = 0;
+    let mut path = quote!(None);
+    let (transport, address) = if let Some(socket) = text("socket") {
+        let socket = unescape(socket);
+        (quote!(Unix), quote!(SocketFilePath(String::from(#socket))))
+    } else if let Some(bt) = text("bt") {
+        (quote!(Bt), quote!(Bluetooth(String::from(#bt))))
+    } else if let Some(dht) = text("dht") {
+        let dht = unescape(dht);
+        (quote!(Dht), quote!(Dht(String::from(#dht))))
+    } else if let (Some(portal), Some(target)) = (text("portal"), text("target")) {
+        let portal = legacy_key(portal)?;
+        let target = legacy_key(target)?;
+        (
+            quote!(Tunnel),
+            quote!(Tunnel {
+                portal: #portal,
+                target: #target,
+            }),
+        )
+    } else if let Some(signal) = text("signal") {
+        let signal = unescape(signal);
+        (quote!(WebRtc), quote!(WebRtc(String::from(#signal))))
+    } else {
+        let name = text("transport")
+            .or_else(|| text("web"))
+            .ok_or("address has no transport")?;
+        let cert_hash = text("cert_hash");
+        let transport = match name {
+            "net" => quote!(Net),
+            "udp" => quote!(Udp),
+            "http" => quote!(Http),
+            "https" => quote!(Https),
+            "ws" => quote!(Ws),
+            "wss" => quote!(Wss),
+            "onion" => quote!(Onion),
+            #[cfg(feature = "quic")]
+            "quic" => match cert_hash {
+                Some(cert_hash) => quote!(Quic {
+                    cert_hash: Some(String::from(#cert_hash)),
+                }),
+                None => quote!(Quic { cert_hash: None }),
+            },
+            _ => return Err(format!("unknown protocol {}", name)),
+        };
+        if let Some(request_path) = text("path").or_else(|| text("web_path")) {
+            if !allows_path(name) {
+                return Err(format!("{} addresses cannot have a path", name));
+            }
+            if !is_url_path(request_path) {
+                return Err(format!("{} is not a URL path", request_path));
+            }
+            path = quote!(Some(String::from(#request_path)));
+        }
+        if cert_hash.is_some() && name != "quic" {
+            return Err(format!("{} addresses cannot have a certificate hash", name));
+        }
+
+        let ip = text("ipv4")
+            .or_else(|| text("ipv6"))
+            .or_else(|| text("ipv6_bracketed"));
+        let address = match (ip, text("url").or_else(|| text("web_host"))) {
+            (Some(_), None) if name == "onion" => {
+                return Err("onion addresses need a v3 .onion host".to_string())
+            }
+            (Some(ip), None) => ip_address(ip)?,
+            (None, Some(host)) => host_address(name, host)?,
+            _ => return Err("address has no host".to_string()),
+        };
+        port = match text("port") {
+            Some(port) => port
+                .parse::<u16>()
+                .map_err(|_| format!("port {} is out of range", port))?,
+            None => default_port(name).ok_or("address has no port")?,
+        };
+        (transport, address)
+    };
+
+    Ok(quote!({
+        use ::multiserver_address_rs::{AddressType, MultiserverAddress, SecurityLayer, Transport};
+        MultiserverAddress {
+            transport: Transport::#transport,
+            port: #port,
+            address: AddressType::#address,
+            path: #path,
+            security: vec![#(#security),*],
+        }
+    }))
+}
+
+fn ip_address(ip: &str) -> Result<TokenStream2, String> {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            let octets = ip.octets();
+            Ok(quote!(Ip(::std::net::IpAddr::V4(::std::net::Ipv4Addr::new(#(#octets),*)))))
+        }
+        Ok(IpAddr::V6(ip)) => {
+            let segments = ip.segments();
+            Ok(quote!(Ip(::std::net::IpAddr::V6(::std::net::Ipv6Addr::new(#(#segments),*)))))
+        }
+        Err(_) => Err(format!("{} is not an IP address", ip)),
+    }
+}
+
+// Only hosts whose normalized form is plain lowercasing, with and without
+// the parser's `url` feature. A numeric last label would be read as IPv4 by
+// the URL host parser, so those are rejected too.
+fn host_address(transport: &str, host: &str) -> Result<TokenStream2, String> {
+    if transport == "onion" && !is_onion_v3(host) {
+        return Err(format!("{} is not a v3 .onion host", host));
+    }
+    let unbracketed = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if unbracketed.parse::<IpAddr>().is_ok() {
+        return ip_address(unbracketed);
+    }
+    let last = host.trim_end_matches('.').rsplit('.').next();
+    let numeric = last
+        .unwrap_or_default()
+        .starts_with(|c: char| c.is_ascii_digit());
+    if !is_ascii_hostname(host) || numeric {
+        return Err(format!("{} is not a plain ASCII hostname", host));
+    }
+    let host = host.to_ascii_lowercase();
+    Ok(quote!(Hostname(String::from(#host))))
+}
+
+// Tunnel keys are written `@<base64>.ed25519`.
+fn legacy_key(st: &str) -> Result<TokenStream2, String> {
+    match st
+        .strip_prefix('@')
+        .and_then(|st| st.strip_suffix(".ed25519"))
+    {
+        Some(data) => ed25519_key(data),
+        None => Err(format!("{} is not an @<base64>.ed25519 key", st)),
+    }
+}
+
+fn ed25519_key(data: &str) -> Result<TokenStream2, String> {
+    let bytes = base64::decode(data).map_err(|_| "pub key is not base64".to_string())?;
+    if bytes.len() != 32 {
+        return Err(format!("pub key is {} bytes, expected 32", bytes.len()));
+    }
+    Ok(quote!(::multiserver_address_rs::__Multikey::from_ed25519(&[#(#bytes),*])))
+}
@@ -0,0 +1,153 @@
+use crate::MultiserverAddress;
+use rand::seq::SliceRandom;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How an address was learned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Provenance {
+    Gossip,
+    Lan,
+    Dns,
+    Room,
+    Manual,
+}
+
+/// An address plus the bookkeeping peer managers keep about it. Timestamps
+/// are milliseconds since the Unix epoch, as elsewhere in SSB.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AnnotatedAddress {
+    pub address: MultiserverAddress,
+    pub source: Provenance,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub failures: u32,
+}
+
+impl AnnotatedAddress {
+    pub fn new(address: MultiserverAddress, source: Provenance) -> AnnotatedAddress {
+        let now = now_ms();
+        AnnotatedAddress {
+            address,
+            source,
+            first_seen: now,
+            last_seen: now,
+            failures: 0,
+        }
+    }
+
+    pub fn mark_seen(&mut self) {
+        self.last_seen = now_ms();
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failures = self.failures.saturating_add(1);
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.mark_seen();
+    }
+}
+
+impl AnnotatedAddress {
+    /// Relative likelihood of dialing this address at `now` (ms since the
+    /// epoch). The weight halves for every day since the address was last
+    /// seen and for every recorded failure, LAN and manually added addresses
+    /// are preferred, and I2P hosts, which need a special dialer, are not.
+    pub fn dial_weight(&self, now: u64) -> f64 {
+        let age_days = now.saturating_sub(self.last_seen) as f64 / 86_400_000.0;
+        let recency = 0.5f64.powf(age_days);
+        let reliability = 0.5f64.powi(self.failures.min(32) as i32);
+        let source = match self.source {
+            Provenance::Lan => 2.0,
+            Provenance::Manual => 1.5,
+            Provenance::Gossip | Provenance::Dns | Provenance::Room => 1.0,
+        };
+        let reachability = if self.address.is_i2p() { 0.5 } else { 1.0 };
+
+        (recency * reliability * source * reachability).max(f64::MIN_POSITIVE)
+    }
+}
+
+/// Picks a dial candidate at random, weighted by `AnnotatedAddress::dial_weight`.
+pub fn choose_weighted<'a, R: Rng + ?Sized>(
+    candidates: &'a [AnnotatedAddress],
+    rng: &mut R,
+) -> Option<&'a AnnotatedAddress> {
+    let now = now_ms();
+    candidates
+        .choose_weighted(rng, |candidate| candidate.dial_weight(now))
+        .ok()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn address() -> MultiserverAddress {
+        MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn annotated_failures_ok() {
+        let mut annotated = AnnotatedAddress::new(address(), Provenance::Gossip);
+        assert_eq!(annotated.first_seen, annotated.last_seen);
+
+        annotated.record_failure();
+        annotated.record_failure();
+        assert_eq!(annotated.failures, 2);
+
+        annotated.record_success();
+        assert_eq!(annotated.failures, 0);
+        assert!(annotated.last_seen >= annotated.first_seen);
+    }
+    #[test]
+    fn choose_weighted_ok() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut fresh = AnnotatedAddress::new(address(), Provenance::Lan);
+        fresh.address.port = 1;
+        let mut failing = AnnotatedAddress::new(address(), Provenance::Gossip);
+        failing.failures = 20;
+        let candidates = [fresh, failing];
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let picks = (0..100)
+            .filter(|_| choose_weighted(&candidates, &mut rng).unwrap().address.port == 1)
+            .count();
+        assert!(picks > 95);
+        assert!(choose_weighted(&[], &mut rng).is_none());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn annotated_serde_ok() {
+        let annotated = AnnotatedAddress::new(address(), Provenance::Lan);
+        let json = serde_json::to_value(&annotated).unwrap();
+        assert_eq!(
+            json["address"],
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        );
+        assert_eq!(json["source"], "lan");
+
+        let decoded: AnnotatedAddress = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, annotated);
+    }
+}
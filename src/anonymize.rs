@@ -0,0 +1,182 @@
+use crate::{AddressType, MultiserverAddress, SecurityLayer, Transport};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use ssb_multiformats::multikey::Multikey;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+type HmacSha256 = Hmac<Sha256>;
+
+impl MultiserverAddress {
+    /// Replaces hosts and keys with pseudonyms derived from HMAC-SHA256 under
+    /// `salt`. The same salt always maps the same host or key to the same
+    /// pseudonym, so datasets stay linkable without revealing the originals.
+    /// Pseudonymous IPv4 hosts land in 240.0.0.0/4 and IPv6 hosts in 100::/64,
+    /// and hostnames end in `.invalid`, so none of them can be dialed. Onion
+    /// hosts stay v3-shaped so that anonymized addresses still parse, but
+    /// their checksum is made up, so Tor will not dial them either.
+    pub fn anonymize(&self, salt: &[u8]) -> MultiserverAddress {
+        let address = match &self.address {
+            AddressType::Ip(IpAddr::V4(ip)) => {
+                let digest = pseudonym(salt, b"ip:", &ip.octets());
+                let mut octets = [0; 4];
+                octets.copy_from_slice(&digest[..4]);
+                octets[0] = 0xf0 | (octets[0] & 0x0f);
+                AddressType::Ip(IpAddr::V4(Ipv4Addr::from(octets)))
+            }
+            AddressType::Ip(IpAddr::V6(ip)) => {
+                let digest = pseudonym(salt, b"ip:", &ip.octets());
+                let mut octets = [0; 16];
+                octets[0] = 0x01;
+                octets[8..].copy_from_slice(&digest[..8]);
+                AddressType::Ip(IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            AddressType::Hostname(host) if self.transport == Transport::Onion => {
+                // A v3 name encodes a 32-byte key, a 2-byte checksum and
+                // the version byte 3.
+                let key = pseudonym(salt, b"host:", host.as_bytes());
+                let mut name = [0; 35];
+                name[..32].copy_from_slice(&key);
+                name[32..34].copy_from_slice(&pseudonym(salt, b"onion:", &key)[..2]);
+                name[34] = 3;
+                AddressType::Hostname(format!("{}.onion", base32(&name)))
+            }
+            AddressType::Hostname(host) => {
+                let digest = pseudonym(salt, b"host:", host.as_bytes());
+                let host = format!("{}.invalid", hex(&digest[..8]));
+                AddressType::Hostname(host)
+            }
+            AddressType::SocketFilePath(path) => {
+                let digest = pseudonym(salt, b"path:", path.as_bytes());
+                AddressType::SocketFilePath(format!("/{}", hex(&digest[..8])))
+            }
+            AddressType::WebRtc(signal) => {
+                let digest = pseudonym(salt, b"webrtc:", signal.as_bytes());
+                AddressType::WebRtc(hex(&digest[..8]))
+            }
+            AddressType::Bluetooth(device) => {
+                let digest = pseudonym(salt, b"bt:", device.as_bytes());
+                AddressType::Bluetooth(hex(&digest[..6]))
+            }
+            AddressType::Dht(channel) => {
+                let digest = pseudonym(salt, b"dht:", channel.as_bytes());
+                AddressType::Dht(hex(&digest[..8]))
+            }
+            AddressType::Tunnel { portal, target } => {
+                let portal = pseudonym(salt, b"key:", portal.to_legacy_string().as_bytes());
+                let target = pseudonym(salt, b"key:", target.to_legacy_string().as_bytes());
+                AddressType::Tunnel {
+                    portal: Multikey::from_ed25519(&portal),
+                    target: Multikey::from_ed25519(&target),
+                }
+            }
+        };
+
+        let pub_key = self.pub_key().map(|pub_key| {
+            let digest = pseudonym(salt, b"key:", pub_key.to_legacy_string().as_bytes());
+            Multikey::from_ed25519(&digest)
+        });
+
+        MultiserverAddress {
+            transport: self.transport.clone(),
+            address,
+            port: self.port,
+            path: self.path.clone(),
+            security: SecurityLayer::layers(pub_key, self.is_noauth()),
+        }
+    }
+}
+
+fn pseudonym(salt: &[u8], domain: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_varkey(salt).expect("HMAC accepts keys of any length");
+    mac.input(domain);
+    mac.input(data);
+
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&mac.result().code());
+    digest
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Lowercase RFC 4648 base32 without padding, as onion names use.
+fn base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &b in bytes {
+        buffer = (buffer << 8) | u16::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[usize::from((buffer >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn anonymize_ipv4_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let anonymized = address.anonymize(b"salt");
+        assert_eq!(anonymized, address.anonymize(b"salt"));
+        assert_ne!(anonymized, address.anonymize(b"pepper"));
+        assert_eq!(anonymized.port, 8008);
+        assert_ne!(anonymized.pub_key(), address.pub_key());
+        match anonymized.address {
+            AddressType::Ip(IpAddr::V4(ip)) => assert_eq!(ip.octets()[0] & 0xf0, 0xf0),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn anonymize_url_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        match address.anonymize(b"salt").address {
+            AddressType::Hostname(host) => assert!(host.ends_with(".invalid")),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn anonymize_onion_ok() {
+        let address = MultiserverAddress::from_str(
+            "onion:vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let anonymized = address.anonymize(b"salt");
+        assert_ne!(anonymized.address, address.address);
+        assert_eq!(
+            MultiserverAddress::from_str(&anonymized.to_string()).unwrap(),
+            anonymized
+        );
+        match anonymized.address {
+            AddressType::Hostname(host) => assert!(host.ends_with("d.onion")),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn anonymize_ipv6_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:[fe80::202:b3ff:fe1e:8329]:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        match address.anonymize(b"salt").address {
+            AddressType::Ip(IpAddr::V6(ip)) => assert_eq!(ip.segments()[..4], [0x100, 0, 0, 0]),
+            _ => panic!(),
+        }
+    }
+}
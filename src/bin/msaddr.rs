@@ -0,0 +1,82 @@
+//! `msaddr parse [<address>]` prints a JSON breakdown of each address.
+//! `msaddr validate [<address>]` exits nonzero if any address is invalid.
+//! Without an address argument, `;`-separated lists are read from stdin.
+use multiserver_address_rs::list::MultiserverAddressList;
+use multiserver_address_rs::MultiserverAddress;
+use serde_json::{json, Value};
+use std::io::{self, Read};
+use std::process;
+use std::str::FromStr;
+
+fn describe(input: &str) -> Value {
+    let address = match MultiserverAddress::from_str(input) {
+        Ok(address) => address,
+        Err(err) => return json!({ "input": input, "error": err.to_string() }),
+    };
+    json!({
+        "input": input,
+        "transport": address.transport.as_str(),
+        "host": address.host_text(),
+        "port": address.port,
+        "path": address.path,
+        "key": address.pub_key().map(|key| key.to_legacy_string()),
+        "scope": address.scope().as_str(),
+    })
+}
+
+fn inputs(arg: Option<String>) -> io::Result<Vec<String>> {
+    let text = match arg {
+        Some(arg) => arg,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    // Newlines never occur inside an address, but `;` may, escaped as `!;`.
+    Ok(text
+        .lines()
+        .flat_map(MultiserverAddressList::entries)
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    // The command is checked before stdin is read, which would otherwise
+    // wait for EOF before printing usage.
+    let validate = match args.next().as_deref() {
+        Some("parse") => false,
+        Some("validate") => true,
+        _ => {
+            eprintln!("usage: msaddr <parse|validate> [<address>]");
+            process::exit(2);
+        }
+    };
+    let inputs = match inputs(args.next()) {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            eprintln!("msaddr: {}", err);
+            process::exit(2);
+        }
+    };
+
+    if validate {
+        let mut valid = true;
+        for input in &inputs {
+            if let Err(err) = MultiserverAddress::from_str(input) {
+                eprintln!("{}: {}", input, err);
+                valid = false;
+            }
+        }
+        if !valid {
+            process::exit(1);
+        }
+    } else {
+        for input in &inputs {
+            println!("{}", describe(input));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    default_port, diagnose, split_unescaped, Error, InputTooLong, MultiserverAddress, NoPortString,
+    PortNotNumeric, MAX_ADDRESS_LEN,
+};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// A lightly checked view of an address that borrows every field from the
+/// input instead of allocating. Meant for scanning large volumes of gossip;
+/// `to_owned` runs the full parser on the addresses that are kept.
+///
+/// Hot loops should scan with views rather than reuse a scratch
+/// `MultiserverAddress`: the owned parser's allocations come from IDNA
+/// normalization returning a new hostname and from decoding the key, which
+/// writing into an existing value would not avoid.
+///
+/// Views can borrow from a caller's arena too, e.g. a `bumpalo` string that
+/// holds a whole gossip round, and are freed with it.
+///
+/// Every field is read from the input, so views compare and hash as their
+/// input string does, and sets of them can be looked up by `&str`.
+#[derive(Debug, Clone, Copy)]
+pub struct MultiserverAddressRef<'a> {
+    input: &'a str,
+    /// Protocol name of the first layer, e.g. `net`.
+    pub transport: &'a str,
+    /// The host for `host:port` transports, otherwise the layer's whole data.
+    pub host: &'a str,
+    /// Zero for transports without a port.
+    pub port: u16,
+    pub path: Option<&'a str>,
+    /// The undecoded base64 of the `shs` layer.
+    pub pub_key: Option<&'a str>,
+}
+
+impl<'a> MultiserverAddressRef<'a> {
+    pub fn parse(input: &'a str) -> Result<MultiserverAddressRef<'a>, Error> {
+        ensure!(
+            input.len() <= MAX_ADDRESS_LEN,
+            InputTooLong {
+                length: input.len()
+            }
+        );
+        let mut layers = split_unescaped(input, b'~').map(|(_, layer)| layer);
+        let first = layers.next().unwrap_or_default();
+        let pub_key = layers.find_map(|layer| layer.strip_prefix("shs:"));
+
+        let colon = first.find(':').ok_or_else(|| diagnose::diagnose(input))?;
+        let (transport, data) = (&first[..colon], &first[colon + 1..]);
+        if transport.is_empty() || data.is_empty() {
+            return Err(diagnose::diagnose(input));
+        }
+
+        let (host, port, path) = match transport {
+            "net" | "udp" | "ws" | "wss" | "onion" | "http" | "https" | "quic" => {
+                let (host_port, path) = match data.find('/') {
+                    Some(i) => (&data[..i], Some(&data[i..])),
+                    None => (data, None),
+                };
+                let mut host_port = host_port;
+                if transport == "quic" {
+                    if let Some((rest, cert_hash)) = host_port.rsplit_once(':') {
+                        if cert_hash.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                            host_port = rest;
+                        }
+                    }
+                }
+                let (host, port) = match host_port.rsplit_once(':') {
+                    Some((host, port)) if !host_port.ends_with(']') => {
+                        (host, u16::from_str(port).context(PortNotNumeric)?)
+                    }
+                    _ => (host_port, default_port(transport).context(NoPortString)?),
+                };
+                let host = host
+                    .strip_prefix('[')
+                    .and_then(|host| host.strip_suffix(']'))
+                    .unwrap_or(host);
+                (host, port, path)
+            }
+            _ => (data, 0, None),
+        };
+
+        Ok(MultiserverAddressRef {
+            input,
+            transport,
+            host,
+            port,
+            path,
+            pub_key,
+        })
+    }
+
+    /// The input this view borrows from.
+    pub fn as_str(&self) -> &'a str {
+        self.input
+    }
+
+    pub fn to_owned(&self) -> Result<MultiserverAddress, Error> {
+        MultiserverAddress::from_str(self.input)
+    }
+}
+
+impl PartialEq for MultiserverAddressRef<'_> {
+    fn eq(&self, other: &MultiserverAddressRef<'_>) -> bool {
+        self.input == other.input
+    }
+}
+
+impl Eq for MultiserverAddressRef<'_> {}
+
+impl Hash for MultiserverAddressRef<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.input.hash(state);
+    }
+}
+
+impl AsRef<str> for MultiserverAddressRef<'_> {
+    fn as_ref(&self) -> &str {
+        self.input
+    }
+}
+
+impl Borrow<str> for MultiserverAddressRef<'_> {
+    fn borrow(&self) -> &str {
+        self.input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed_parse_ok() {
+        let input =
+            "https:room.example.com:443/alias~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddressRef::parse(input).unwrap();
+        assert_eq!(address.transport, "https");
+        assert_eq!(address.host, "room.example.com");
+        assert_eq!(address.port, 443);
+        assert_eq!(address.path, Some("/alias"));
+        assert_eq!(
+            address.pub_key,
+            Some("HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=")
+        );
+        assert_eq!(
+            address.to_owned().unwrap(),
+            MultiserverAddress::from_str(input).unwrap()
+        );
+
+        let ipv6 = MultiserverAddressRef::parse("net:fe80:0:0:0:202:b3ff:fe1e:8329:8008").unwrap();
+        assert_eq!(ipv6.host, "fe80:0:0:0:202:b3ff:fe1e:8329");
+        assert_eq!(ipv6.pub_key, None);
+
+        let default_port = MultiserverAddressRef::parse("wss:[::1]/ssb~noauth").unwrap();
+        assert_eq!((default_port.host, default_port.port), ("::1", 443));
+    }
+    #[test]
+    fn borrowed_as_str_ok() {
+        use std::collections::HashSet;
+
+        let input = "net:host.com:8008~noauth";
+        let address = MultiserverAddressRef::parse(input).unwrap();
+        assert_eq!(address.as_ref(), input);
+        let mut seen = HashSet::new();
+        seen.insert(address);
+        assert!(seen.contains(input));
+        assert!(!seen.contains("net:host.com:8009~noauth"));
+    }
+    #[test]
+    fn borrowed_parse_err() {
+        assert!(MultiserverAddressRef::parse("net:host.com~shs:KEY").is_err());
+        assert!(MultiserverAddressRef::parse("nonsense").is_err());
+    }
+}
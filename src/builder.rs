@@ -0,0 +1,244 @@
+use crate::{
+    address_from_host, check_onion, is_url_path, AddressType, Error, FieldNotAllowed, HostInvalid,
+    MultiserverAddress, NoAddressString, NoPortString, PathInvalid, ProtocolUnknown, SecurityLayer,
+    Transport,
+};
+use snafu::{ensure, OptionExt};
+use ssb_multiformats::multikey::Multikey;
+use std::net::IpAddr;
+
+/// Assembles a `MultiserverAddress` field by field, applying the same rules
+/// as the parser when `build` is called. The transport defaults to `net`.
+///
+/// Only `host:port` transports and `unix` can be built; `bt`, `dht`,
+/// `tunnel` and `webrtc` addresses have no setter for their data and are
+/// rejected, as is `quic` without the `quic` feature.
+#[derive(Debug, Clone, Default)]
+pub struct MultiserverAddressBuilder {
+    transport: Option<Transport>,
+    address: Option<Result<AddressType, String>>,
+    port: Option<u16>,
+    path: Option<String>,
+    pub_key: Option<Multikey>,
+    noauth: bool,
+}
+
+impl MultiserverAddressBuilder {
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    pub fn ip(mut self, ip: IpAddr) -> Self {
+        self.address = Some(Ok(AddressType::Ip(ip)));
+        self
+    }
+
+    /// An IP literal or hostname; hostnames are validated in `build`.
+    pub fn host(mut self, host: &str) -> Self {
+        self.address = Some(Err(host.to_string()));
+        self
+    }
+
+    /// A unix socket path. Also switches the transport to `unix`.
+    pub fn socket_path(mut self, path: &str) -> Self {
+        self.transport = Some(Transport::Unix);
+        self.address = Some(Ok(AddressType::SocketFilePath(path.to_string())));
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    pub fn shs_key(mut self, pub_key: Multikey) -> Self {
+        self.pub_key = Some(pub_key);
+        self
+    }
+
+    /// Adds a `~noauth` layer.
+    pub fn noauth(mut self) -> Self {
+        self.noauth = true;
+        self
+    }
+
+    pub fn build(self) -> Result<MultiserverAddress, Error> {
+        let transport = self.transport.unwrap_or(Transport::Net);
+        ensure!(
+            cfg!(feature = "quic") || !matches!(transport, Transport::Quic { .. }),
+            ProtocolUnknown {
+                name: transport.as_str()
+            }
+        );
+        ensure!(
+            transport.has_host_port() || transport == Transport::Unix,
+            HostInvalid {
+                transport: transport.clone()
+            }
+        );
+        let address = match self.address.context(NoAddressString)? {
+            Ok(address) => address,
+            Err(host) => address_from_host(&host)?,
+        };
+        let port = match transport {
+            Transport::Unix => 0,
+            _ => self
+                .port
+                .or_else(|| transport.default_port())
+                .context(NoPortString)?,
+        };
+
+        ensure!(
+            transport.allows_path() || self.path.is_none(),
+            FieldNotAllowed {
+                transport: transport.clone(),
+                field: "path"
+            }
+        );
+        ensure!(self.path.as_deref().is_none_or(is_url_path), PathInvalid);
+        let is_socket = matches!(address, AddressType::SocketFilePath(_));
+        ensure!(
+            is_socket == (transport == Transport::Unix),
+            HostInvalid {
+                transport: transport.clone()
+            }
+        );
+        check_onion(&transport, &address)?;
+
+        Ok(MultiserverAddress {
+            transport,
+            address,
+            port,
+            path: self.path,
+            security: SecurityLayer::layers(self.pub_key, self.noauth),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
+
+    const ADDRESS: &str = "net:10.0.0.1:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+
+    #[test]
+    fn builder_ok() {
+        let parsed = MultiserverAddress::from_str(ADDRESS).unwrap();
+        let built = MultiserverAddress::builder()
+            .ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+            .port(8008)
+            .shs_key(parsed.pub_key().cloned().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(built, parsed);
+        assert_eq!(built.to_string(), ADDRESS);
+
+        let room = MultiserverAddress::builder()
+            .transport(Transport::Https)
+            .host("room.example.com")
+            .port(443)
+            .path("/alias")
+            .build()
+            .unwrap();
+        assert_eq!(room.to_string(), "https:room.example.com/alias");
+    }
+    #[test]
+    fn builder_round_trip_ok() {
+        let onion = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion";
+        let mut transports = vec![
+            (Transport::Net, "host.com"),
+            (Transport::Udp, "10.0.0.1"),
+            (Transport::Http, "host.com"),
+            (Transport::Https, "host.com"),
+            (Transport::Ws, "host.com"),
+            (Transport::Wss, "host.com"),
+            (Transport::Onion, onion),
+        ];
+        if cfg!(feature = "quic") {
+            transports.push((
+                Transport::Quic {
+                    cert_hash: Some("sha256-AbCd".to_string()),
+                },
+                "host.com",
+            ));
+        }
+        for (transport, host) in transports {
+            let built = MultiserverAddress::builder()
+                .transport(transport)
+                .host(host)
+                .port(8008)
+                .build()
+                .unwrap();
+            assert_eq!(
+                MultiserverAddress::from_str(&built.to_string()).unwrap(),
+                built
+            );
+        }
+
+        let unix = MultiserverAddress::builder()
+            .socket_path("/tmp/s")
+            .port(9)
+            .build()
+            .unwrap();
+        assert_eq!(unix.port, 0);
+        assert_eq!(
+            MultiserverAddress::from_str(&unix.to_string()).unwrap(),
+            unix
+        );
+    }
+
+    #[test]
+    fn builder_err() {
+        match MultiserverAddress::builder().port(8008).build() {
+            Err(Error::NoAddressString {}) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match MultiserverAddress::builder().host("host.com").build() {
+            Err(Error::NoPortString {}) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match MultiserverAddress::builder()
+            .host("host.com")
+            .port(8008)
+            .path("/alias")
+            .build()
+        {
+            Err(Error::FieldNotAllowed { field: "path", .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        for transport in [
+            Transport::Bt,
+            Transport::Dht,
+            Transport::Tunnel,
+            Transport::WebRtc,
+        ] {
+            match MultiserverAddress::builder()
+                .transport(transport)
+                .ip(IpAddr::V4(Ipv4Addr::LOCALHOST))
+                .port(1)
+                .build()
+            {
+                Err(Error::HostInvalid { .. }) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        #[cfg(not(feature = "quic"))]
+        match MultiserverAddress::builder()
+            .transport(Transport::Quic { cert_hash: None })
+            .host("host.com")
+            .port(443)
+            .build()
+        {
+            Err(Error::ProtocolUnknown { .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
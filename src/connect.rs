@@ -0,0 +1,83 @@
+use crate::{unsupported, AddressType, MultiserverAddress, Transport};
+use ssb_multiformats::multikey::Multikey;
+use std::io;
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// An open, not yet authenticated connection to a multiserver address.
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(TcpStream),
+    WebSocket(Box<WebSocketStream<MaybeTlsStream<TcpStream>>>),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl MultiserverAddress {
+    /// Opens the transport of this address: TCP for `net`, a WebSocket for
+    /// `ws` and `wss`, and a Unix socket for `unix`. Returns the stream with
+    /// the key the peer must prove in the secret handshake, if any. Other
+    /// transports fail with `io::ErrorKind::Unsupported`.
+    pub async fn connect(&self) -> io::Result<(Stream, Option<Multikey>)> {
+        let stream = match (&self.transport, &self.address) {
+            (Transport::Net, AddressType::Ip(ip)) => {
+                Stream::Tcp(TcpStream::connect((*ip, self.port)).await?)
+            }
+            (Transport::Net, AddressType::Hostname(host)) => {
+                Stream::Tcp(TcpStream::connect((host.as_str(), self.port)).await?)
+            }
+            (Transport::Ws, _) | (Transport::Wss, _) => {
+                let url = self.websocket_url().ok_or_else(|| unsupported(self))?;
+                let (stream, _) = tokio_tungstenite::connect_async(url)
+                    .await
+                    .map_err(io::Error::other)?;
+                Stream::WebSocket(Box::new(stream))
+            }
+            #[cfg(unix)]
+            (Transport::Unix, AddressType::SocketFilePath(path)) => {
+                Stream::Unix(UnixStream::connect(path).await?)
+            }
+            _ => return Err(unsupported(self)),
+        };
+        Ok((stream, self.handshake_key()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stream;
+    use crate::MultiserverAddress;
+    use std::net::TcpListener;
+    use std::str::FromStr;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn connect_net_ok() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let address = MultiserverAddress::from_str(&format!(
+            "net:127.0.0.1:{}~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            port
+        ))
+        .unwrap();
+        let (stream, pub_key) = block_on(address.connect()).unwrap();
+        assert!(matches!(stream, Stream::Tcp(_)));
+        assert_eq!(pub_key, address.pub_key().cloned());
+    }
+
+    #[test]
+    fn connect_unsupported_err() {
+        let address = MultiserverAddress::from_str("bt:0123456789ab").unwrap();
+        let err = block_on(address.connect()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+}
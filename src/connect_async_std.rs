@@ -0,0 +1,76 @@
+use crate::{unsupported, AddressType, MultiserverAddress, Transport};
+use async_std::net::TcpStream;
+#[cfg(unix)]
+use async_std::os::unix::net::UnixStream;
+use async_tungstenite::async_std::ConnectStream;
+use async_tungstenite::WebSocketStream;
+use ssb_multiformats::multikey::Multikey;
+use std::io;
+
+/// An open, not yet authenticated connection on the async-std runtime. The
+/// streams implement the `futures-io` traits.
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(TcpStream),
+    WebSocket(Box<WebSocketStream<ConnectStream>>),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl MultiserverAddress {
+    /// `connect` for applications that run on async-std instead of tokio.
+    /// Opens the same transports and fails the same way.
+    pub async fn connect_async_std(&self) -> io::Result<(Stream, Option<Multikey>)> {
+        let stream = match (&self.transport, &self.address) {
+            (Transport::Net, AddressType::Ip(ip)) => {
+                Stream::Tcp(TcpStream::connect((*ip, self.port)).await?)
+            }
+            (Transport::Net, AddressType::Hostname(host)) => {
+                Stream::Tcp(TcpStream::connect((host.as_str(), self.port)).await?)
+            }
+            (Transport::Ws, _) | (Transport::Wss, _) => {
+                let url = self.websocket_url().ok_or_else(|| unsupported(self))?;
+                let (stream, _) = async_tungstenite::async_std::connect_async(url)
+                    .await
+                    .map_err(io::Error::other)?;
+                Stream::WebSocket(Box::new(stream))
+            }
+            #[cfg(unix)]
+            (Transport::Unix, AddressType::SocketFilePath(path)) => {
+                Stream::Unix(UnixStream::connect(path).await?)
+            }
+            _ => return Err(unsupported(self)),
+        };
+        Ok((stream, self.handshake_key()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stream;
+    use crate::MultiserverAddress;
+    use async_std::task::block_on;
+    use std::net::TcpListener;
+    use std::str::FromStr;
+
+    #[test]
+    fn connect_async_std_net_ok() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let address = MultiserverAddress::from_str(&format!(
+            "net:127.0.0.1:{}~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            port
+        ))
+        .unwrap();
+        let (stream, pub_key) = block_on(address.connect_async_std()).unwrap();
+        assert!(matches!(stream, Stream::Tcp(_)));
+        assert_eq!(pub_key, address.pub_key().cloned());
+    }
+
+    #[test]
+    fn connect_async_std_unsupported_err() {
+        let address = MultiserverAddress::from_str("bt:0123456789ab").unwrap();
+        let err = block_on(address.connect_async_std()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+}
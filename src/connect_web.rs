@@ -0,0 +1,47 @@
+use crate::{unsupported, MultiserverAddress, Transport};
+use js_sys::Promise;
+use ssb_multiformats::multikey::Multikey;
+use std::io;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{BinaryType, WebSocket};
+
+/// An open, not yet authenticated connection from a browser, where
+/// WebSockets are the only transport.
+#[derive(Debug)]
+pub enum Stream {
+    WebSocket(WebSocket),
+}
+
+impl MultiserverAddress {
+    /// Opens a browser WebSocket for `ws` and `wss` addresses and waits until
+    /// it is open. Returns the stream with the key the peer must prove in the
+    /// secret handshake, if any. Other transports fail with
+    /// `io::ErrorKind::Unsupported`.
+    pub async fn connect(&self) -> io::Result<(Stream, Option<Multikey>)> {
+        let url = match self.transport {
+            Transport::Ws | Transport::Wss => self.websocket_url(),
+            _ => None,
+        }
+        .ok_or_else(|| unsupported(self))?;
+        let socket = WebSocket::new(&url)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, url.clone()))?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        // Settles on whichever of `open` and `error` fires first. Browsers
+        // do not say why a socket failed, so every failure is a refusal.
+        let opened = Promise::new(&mut |resolve, reject| {
+            socket.set_onopen(Some(&resolve));
+            socket.set_onerror(Some(&reject));
+        });
+        let result = JsFuture::from(opened).await;
+        socket.set_onopen(None);
+        socket.set_onerror(None);
+        result.map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("cannot connect to {}", self),
+            )
+        })?;
+        Ok((Stream::WebSocket(socket), self.handshake_key()))
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{default_port, split_unescaped, Error};
+
+// Where and why `st` failed to parse, for inputs the address regex rejected.
+pub(crate) fn diagnose(st: &str) -> Error {
+    let (offset, segment, expected) = locate(st);
+    Error::Unexpected {
+        input: st.to_string(),
+        offset,
+        segment,
+        expected,
+    }
+}
+
+// Renders the input with a caret under the offending byte.
+pub(crate) fn render(input: &str, offset: usize, segment: &str, expected: &str) -> String {
+    let column = input
+        .get(..offset)
+        .map_or(offset, |before| before.chars().count());
+    format!(
+        "Expected {} in {} at byte {}\n  {}\n  {}^",
+        expected,
+        segment,
+        offset,
+        input,
+        " ".repeat(column)
+    )
+}
+
+type Spot = (usize, &'static str, &'static str);
+
+fn locate(st: &str) -> Spot {
+    let mut layers = split_unescaped(st, b'~');
+    let (_, first) = layers.next().unwrap_or_default();
+    let colon = match first.find(':') {
+        Some(colon) => colon,
+        None => return (first.len(), "transport", "`:` after the transport name"),
+    };
+    let data = &first[colon + 1..];
+    let name = &first[..colon];
+    let spot = match name {
+        "net" | "udp" | "http" | "https" | "ws" | "wss" | "onion" | "quic" => {
+            host_port(data, default_port(name).is_some())
+        }
+        "unix" => non_empty(data, "a socket path"),
+        "dht" => non_empty(data, "an invite channel"),
+        "webrtc" => non_empty(data, "signalling data"),
+        "bt" => bluetooth(data),
+        "tunnel" => tunnel(data),
+        _ => return (0, "transport", "a known transport name"),
+    };
+    if let Some((offset, segment, expected)) = spot {
+        return (colon + 1 + offset, segment, expected);
+    }
+
+    let mut seen_key = false;
+    let mut seen_noauth = false;
+    for (at, layer) in layers {
+        if seen_noauth {
+            return (at, "security layer", "nothing after `~noauth`");
+        }
+        if layer == "noauth" {
+            seen_noauth = true;
+            continue;
+        }
+        let colon = match layer.find(':') {
+            Some(colon) if &layer[..colon] == "shs" && !seen_key => colon,
+            _ => return (at, "security layer", "`shs:<key>` or `noauth`"),
+        };
+        let key = &layer[colon + 1..];
+        let key = key.rfind('.').map_or(key, |dot| &key[..dot]);
+        if !key.ends_with('=') {
+            return (at + colon + 1, "key", "a base64 key ending in `=`");
+        }
+        seen_key = true;
+    }
+    (0, "address", "a multiserver address")
+}
+
+fn non_empty(data: &str, expected: &'static str) -> Option<Spot> {
+    if data.is_empty() {
+        Some((0, "address", expected))
+    } else {
+        None
+    }
+}
+
+fn bluetooth(data: &str) -> Option<Spot> {
+    let hex = data.bytes().take_while(u8::is_ascii_hexdigit).count();
+    if hex == 12 && data.len() == 12 {
+        None
+    } else {
+        Some((hex.min(12), "address", "12 hex digits"))
+    }
+}
+
+fn tunnel(data: &str) -> Option<Spot> {
+    if !data.starts_with('@') {
+        return Some((0, "address", "`@` starting the portal key"));
+    }
+    match data.find(':') {
+        None => Some((data.len(), "address", "`:` between portal and target")),
+        Some(colon) if !data[colon + 1..].starts_with('@') => {
+            Some((colon + 1, "address", "`@` starting the target key"))
+        }
+        _ => None,
+    }
+}
+
+// `port_optional` for transports with a default port.
+fn host_port(data: &str, port_optional: bool) -> Option<Spot> {
+    let data = &data[..data.find('/').unwrap_or(data.len())];
+    let host_end = if data.starts_with('[') {
+        match data.find(']') {
+            Some(close) => close + 1,
+            None => return Some((data.len(), "address", "`]` closing the IPv6 host")),
+        }
+    } else if data.matches(':').count() >= 8 {
+        // An unbracketed IPv6 host: the port follows the last colon.
+        data.rfind(':').unwrap()
+    } else {
+        data.find(':').unwrap_or(data.len())
+    };
+    if host_end == 0 {
+        return Some((0, "address", "an IP address or hostname"));
+    }
+    let rest = &data[host_end..];
+    if rest.is_empty() && port_optional {
+        return None;
+    }
+    if !rest.starts_with(':') {
+        return Some((host_end, "port", "`:` followed by a port number"));
+    }
+    let port = &rest[1..];
+    let digits = port.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 || !(digits == port.len() || port[digits..].starts_with(':')) {
+        return Some((host_end + 1 + digits, "port", "a decimal port number"));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, MultiserverAddress};
+    use std::str::FromStr;
+
+    fn spot(st: &str) -> (usize, &'static str) {
+        match MultiserverAddress::from_str(st) {
+            Err(Error::Unexpected {
+                offset, segment, ..
+            }) => (offset, segment),
+            other => panic!("{} gave {:?}", st, other),
+        }
+    }
+
+    #[test]
+    fn diagnose_segment_ok() {
+        assert_eq!(spot("smtp:host.com:25"), (0, "transport"));
+        assert_eq!(spot("net"), (3, "transport"));
+        assert_eq!(spot("net:host.com"), (12, "port"));
+        assert_eq!(spot("net:host.com:80a"), (15, "port"));
+        assert_eq!(spot("net::8008"), (4, "address"));
+        assert_eq!(spot("bt:0123456789xy"), (13, "address"));
+        assert_eq!(spot("net:host.com:8008~shs:abc"), (22, "key"));
+        assert_eq!(
+            spot("net:host.com:8008~noauth~noauth"),
+            (25, "security layer")
+        );
+        assert_eq!(
+            spot("net:1.2.3.4:8008~evil:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="),
+            (17, "security layer")
+        );
+    }
+
+    #[test]
+    fn diagnose_render_ok() {
+        let err = MultiserverAddress::from_str("net:host.com:80a").unwrap_err();
+        assert!(err.is_syntax());
+        assert_eq!(
+            err.to_string(),
+            "Expected a decimal port number in port at byte 15\n  net:host.com:80a\n                 ^"
+        );
+    }
+}
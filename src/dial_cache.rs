@@ -0,0 +1,138 @@
+use crate::MultiserverAddress;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DialOutcome {
+    Success,
+    Refused,
+    Timeout,
+    AuthFailed,
+}
+
+/// Remembers the most recent connect outcome per address for `ttl`. Entries
+/// are keyed by the canonical address, so `net:Host.com.:8008` and
+/// `net:host.com:8008` share an entry. When full, the oldest entry is evicted.
+#[derive(Debug, Clone)]
+pub struct DialCache {
+    entries: HashMap<MultiserverAddress, (DialOutcome, Instant)>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl DialCache {
+    pub fn new(capacity: usize, ttl: Duration) -> DialCache {
+        DialCache {
+            entries: HashMap::new(),
+            capacity,
+            ttl,
+        }
+    }
+
+    pub fn record(&mut self, address: MultiserverAddress, outcome: DialOutcome, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        let address = address.canonicalize();
+        if !self.entries.contains_key(&address) && self.entries.len() >= self.capacity {
+            self.purge_expired(now);
+            if self.entries.len() >= self.capacity {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, at))| *at)
+                    .map(|(address, _)| address.clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+        self.entries.insert(address, (outcome, now));
+    }
+
+    pub fn get(&self, address: &MultiserverAddress, now: Instant) -> Option<DialOutcome> {
+        self.entries
+            .get(&address.canonicalize())
+            .filter(|(_, at)| now.saturating_duration_since(*at) < self.ttl)
+            .map(|(outcome, _)| *outcome)
+    }
+
+    /// Whether the last attempt within the TTL failed in any way.
+    pub fn is_known_dead(&self, address: &MultiserverAddress, now: Instant) -> bool {
+        self.get(address, now)
+            .is_some_and(|outcome| outcome != DialOutcome::Success)
+    }
+
+    pub fn purge_expired(&mut self, now: Instant) {
+        let ttl = self.ttl;
+        self.entries
+            .retain(|_, (_, at)| now.saturating_duration_since(*at) < ttl);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn address(port: u16) -> MultiserverAddress {
+        MultiserverAddress::from_str(&format!(
+            "net:host.com:{}~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            port
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn dial_cache_ttl_ok() {
+        let now = Instant::now();
+        let mut cache = DialCache::new(8, Duration::from_secs(60));
+        cache.record(address(1), DialOutcome::Refused, now);
+
+        assert!(cache.is_known_dead(&address(1), now));
+        assert_eq!(cache.get(&address(2), now), None);
+        assert_eq!(cache.get(&address(1), now + Duration::from_secs(60)), None);
+
+        cache.record(address(1), DialOutcome::Success, now);
+        assert!(!cache.is_known_dead(&address(1), now));
+    }
+    #[test]
+    fn dial_cache_capacity_ok() {
+        let now = Instant::now();
+        let mut cache = DialCache::new(2, Duration::from_secs(60));
+        cache.record(address(1), DialOutcome::Timeout, now);
+        cache.record(
+            address(2),
+            DialOutcome::Timeout,
+            now + Duration::from_secs(1),
+        );
+        cache.record(
+            address(3),
+            DialOutcome::Timeout,
+            now + Duration::from_secs(2),
+        );
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&address(1), now), None);
+        assert!(cache.is_known_dead(&address(3), now + Duration::from_secs(2)));
+    }
+    #[test]
+    fn dial_cache_canonical_ok() {
+        let now = Instant::now();
+        let mut cache = DialCache::new(8, Duration::from_secs(60));
+        let spelled = MultiserverAddress::from_str(
+            "net:Host.com.:1~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        cache.record(spelled, DialOutcome::Refused, now);
+        assert!(cache.is_known_dead(&address(1), now));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::annotated;
+use crate::{split_unescaped, MultiserverAddress};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiscoveredAddress {
+    pub address: MultiserverAddress,
+    pub source: annotated::Provenance,
+}
+
+/// A discovery mechanism (LAN broadcasts, DNS, room member lists, manual
+/// config) that yields addresses as it finds them. Any `Send` stream of
+/// `DiscoveredAddress` is a source.
+pub trait AddressSource {
+    fn into_stream(self: Box<Self>) -> BoxStream<'static, DiscoveredAddress>;
+}
+
+impl<S> AddressSource for S
+where
+    S: Stream<Item = DiscoveredAddress> + Send + 'static,
+{
+    fn into_stream(self: Box<Self>) -> BoxStream<'static, DiscoveredAddress> {
+        (*self).boxed()
+    }
+}
+
+/// A fixed list of addresses, such as bootstrap peers from a config file.
+pub fn manual<I>(addresses: I) -> impl AddressSource
+where
+    I: IntoIterator<Item = MultiserverAddress>,
+    I::IntoIter: Send + 'static,
+{
+    stream::iter(addresses).map(|address| DiscoveredAddress {
+        address,
+        source: annotated::Provenance::Manual,
+    })
+}
+
+/// Merges all sources into one stream, yielding addresses as soon as any
+/// source produces them.
+pub fn discover(
+    sources: Vec<Box<dyn AddressSource + Send>>,
+) -> impl Stream<Item = DiscoveredAddress> {
+    stream::select_all(sources.into_iter().map(AddressSource::into_stream))
+}
+
+/// The addresses in a LAN discovery broadcast, a `;`-separated address list.
+/// Trailing whitespace and NUL padding are ignored, as are entries that do
+/// not parse, so one peer's odd transport does not hide the rest.
+pub fn parse_announcement(payload: &[u8]) -> Vec<MultiserverAddress> {
+    let payload = String::from_utf8_lossy(payload);
+    let payload = payload.trim_end_matches(|c: char| c == '\0' || c.is_whitespace());
+    split_unescaped(payload, b';')
+        .filter_map(|(_, entry)| entry.trim().parse().ok())
+        .collect()
+}
+
+/// The broadcast payload announcing `addresses`.
+pub fn build_announcement(addresses: &[MultiserverAddress]) -> Vec<u8> {
+    let entries: Vec<String> = addresses.iter().map(ToString::to_string).collect();
+    entries.join(";").into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::str::FromStr;
+
+    #[test]
+    fn discover_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let lan = stream::iter(vec![DiscoveredAddress {
+            address: address.clone(),
+            source: annotated::Provenance::Lan,
+        }]);
+
+        let sources: Vec<Box<dyn AddressSource + Send>> =
+            vec![Box::new(manual(vec![address.clone()])), Box::new(lan)];
+        let discovered: Vec<DiscoveredAddress> = block_on(discover(sources).collect());
+
+        assert_eq!(discovered.len(), 2);
+        assert!(discovered
+            .iter()
+            .any(|found| found.source == annotated::Provenance::Lan));
+        assert!(discovered.iter().all(|found| found.address == address));
+    }
+
+    #[test]
+    fn announcement_round_trip_ok() {
+        let addresses = vec![
+            MultiserverAddress::from_str(
+                "net:192.168.1.5:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            )
+            .unwrap(),
+            MultiserverAddress::from_str(
+                "ws:192.168.1.5:8989~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            )
+            .unwrap(),
+        ];
+        let mut payload = build_announcement(&addresses);
+        assert_eq!(parse_announcement(&payload), addresses);
+
+        payload.extend_from_slice(b";carrier-pigeon:coop\n\0\0");
+        assert_eq!(parse_announcement(&payload), addresses);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{check_limits, split_unescaped, Error, SegmentNameMissing};
+use std::borrow::Cow;
+
+/// Receives the structure of a `;`-separated multiserver address list as it
+/// is scanned. Segment data is passed through uninterpreted, borrowed from
+/// the input.
+pub trait AddressSink {
+    fn begin_address(&mut self) {}
+    fn segment(&mut self, name: &str, data: &str);
+    fn end_address(&mut self) {}
+}
+
+/// Splits `input` into addresses on `;`, layers on `~`, and each layer into
+/// its name and data at the first `:`. A layer without `:` (such as `noauth`)
+/// is reported with empty data. Separators escaped with `!` are part of the
+/// data, which is passed on still escaped. Scanning stops at the first layer
+/// with an empty name, or at the first address over the length, segment or
+/// control character limits that `MultiserverAddress::from_str` enforces.
+pub fn parse_events<S: AddressSink + ?Sized>(input: &str, sink: &mut S) -> Result<(), Error> {
+    let mut offset = 0;
+
+    for (_, address) in split_unescaped(input, b';') {
+        check_limits(address)?;
+        sink.begin_address();
+        for (_, layer) in split_unescaped(address, b'~') {
+            let (name, data) = name_and_data(layer);
+            if name.is_empty() {
+                return SegmentNameMissing { offset }.fail();
+            }
+            sink.segment(name, data);
+            offset += layer.len() + 1;
+        }
+        sink.end_address();
+    }
+
+    Ok(())
+}
+
+/// Splits a single address into its `(name, data)` layers without
+/// interpreting any protocol, for applications that handle experimental
+/// transports themselves. Splitting follows `parse_events`: data is borrowed
+/// still escaped, see `unescape`. Iteration ends after the first error.
+pub fn segments(input: &str) -> impl Iterator<Item = Result<(&str, &str), Error>> {
+    let mut limit = check_limits(input).err();
+    let mut failed = false;
+    split_unescaped(input, b'~').map_while(move |(offset, layer)| {
+        if failed {
+            return None;
+        }
+        if let Some(err) = limit.take() {
+            failed = true;
+            return Some(Err(err));
+        }
+        let (name, data) = name_and_data(layer);
+        failed = name.is_empty();
+        Some(if failed {
+            SegmentNameMissing { offset }.fail()
+        } else {
+            Ok((name, data))
+        })
+    })
+}
+
+/// Removes `!` escapes from segment data, borrowing when there are none.
+pub fn unescape(data: &str) -> Cow<'_, str> {
+    if data.contains('!') {
+        Cow::Owned(crate::unescape(data))
+    } else {
+        Cow::Borrowed(data)
+    }
+}
+
+fn name_and_data(layer: &str) -> (&str, &str) {
+    match layer.find(':') {
+        Some(i) => (&layer[..i], &layer[i + 1..]),
+        None => (layer, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl AddressSink for Recorder {
+        fn begin_address(&mut self) {
+            self.events.push("begin".to_string());
+        }
+        fn segment(&mut self, name: &str, data: &str) {
+            self.events.push(format!("{}={}", name, data));
+        }
+        fn end_address(&mut self) {
+            self.events.push("end".to_string());
+        }
+    }
+
+    #[test]
+    fn parse_events_ok() {
+        let mut recorder = Recorder::default();
+        parse_events(
+            "net:host.com:8008~shs:KEY;ws:host.com:80~noauth",
+            &mut recorder,
+        )
+        .unwrap();
+        assert_eq!(
+            recorder.events,
+            vec![
+                "begin",
+                "net=host.com:8008",
+                "shs=KEY",
+                "end",
+                "begin",
+                "ws=host.com:80",
+                "noauth=",
+                "end"
+            ]
+        );
+    }
+    #[test]
+    fn parse_events_err() {
+        let mut recorder = Recorder::default();
+        match parse_events("net:host.com:8008~:KEY", &mut recorder) {
+            Err(Error::SegmentNameMissing { offset }) => assert_eq!(offset, 18),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn segments_ok() {
+        let segments: Vec<_> = segments("unix:/tmp/a!:b.sock~x-experimental:a!~b~noauth")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                ("unix", "/tmp/a!:b.sock"),
+                ("x-experimental", "a!~b"),
+                ("noauth", "")
+            ]
+        );
+        assert_eq!(unescape(segments[1].1), "a~b");
+        assert!(matches!(unescape("plain"), Cow::Borrowed("plain")));
+    }
+    #[test]
+    fn segments_err() {
+        let mut segments = segments("net:host.com:8008~:KEY~noauth");
+        assert_eq!(segments.next().unwrap().unwrap(), ("net", "host.com:8008"));
+        match segments.next() {
+            Some(Err(Error::SegmentNameMissing { offset })) => assert_eq!(offset, 18),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(segments.next().is_none());
+    }
+    #[test]
+    fn limits_err() {
+        let long = format!("net:{}.com:8008", "a".repeat(crate::MAX_ADDRESS_LEN));
+        let mut recorder = Recorder::default();
+        let input = format!("net:host.com:8008;{}", long);
+        assert!(matches!(
+            parse_events(&input, &mut recorder),
+            Err(Error::InputTooLong { .. })
+        ));
+        assert_eq!(recorder.events, ["begin", "net=host.com:8008", "end"]);
+
+        let many = format!("net:host.com:8008{}", "~noauth".repeat(crate::MAX_SEGMENTS));
+        let mut segments = segments(&many);
+        assert!(matches!(
+            segments.next(),
+            Some(Err(Error::TooManySegments { .. }))
+        ));
+        assert!(segments.next().is_none());
+    }
+}
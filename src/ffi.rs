@@ -0,0 +1,188 @@
+//! C ABI for linking the parser into C and Go programs. The matching header
+//! is `include/msaddr.h`, generated with `cbindgen --config cbindgen.toml`.
+//!
+//! Addresses are opaque pointers owned by the caller until passed to
+//! `msaddr_free`. Strings returned by accessors are newly allocated and must
+//! be released with `msaddr_string_free`.
+
+use crate::{ErrorKind, MultiserverAddress};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::str::FromStr;
+
+pub const MSADDR_OK: c_int = 0;
+/// A required pointer was null or the input was not UTF-8.
+pub const MSADDR_ERR_INVALID_ARGUMENT: c_int = -1;
+pub const MSADDR_ERR_SYNTAX: c_int = 1;
+pub const MSADDR_ERR_SEMANTIC: c_int = 2;
+pub const MSADDR_ERR_LIMIT: c_int = 3;
+
+/// Parses the NUL-terminated `input` and stores the address in `*out`.
+/// Returns `MSADDR_OK` or an `MSADDR_ERR_*` code, leaving `*out` untouched
+/// on failure.
+///
+/// # Safety
+///
+/// `input` must be a valid NUL-terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn msaddr_parse(
+    input: *const c_char,
+    out: *mut *mut MultiserverAddress,
+) -> c_int {
+    if input.is_null() || out.is_null() {
+        return MSADDR_ERR_INVALID_ARGUMENT;
+    }
+    let input = match CStr::from_ptr(input).to_str() {
+        Ok(input) => input,
+        Err(_) => return MSADDR_ERR_INVALID_ARGUMENT,
+    };
+    match MultiserverAddress::from_str(input) {
+        Ok(address) => {
+            *out = Box::into_raw(Box::new(address));
+            MSADDR_OK
+        }
+        Err(err) => match err.kind() {
+            ErrorKind::Syntax => MSADDR_ERR_SYNTAX,
+            ErrorKind::Semantic => MSADDR_ERR_SEMANTIC,
+            ErrorKind::Limit => MSADDR_ERR_LIMIT,
+        },
+    }
+}
+
+/// Releases an address from `msaddr_parse`. Null is ignored.
+///
+/// # Safety
+///
+/// `address` must come from `msaddr_parse` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn msaddr_free(address: *mut MultiserverAddress) {
+    if !address.is_null() {
+        drop(Box::from_raw(address));
+    }
+}
+
+/// The transport name, e.g. `net`.
+///
+/// # Safety
+///
+/// `address` must be a live address from `msaddr_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn msaddr_transport(address: *const MultiserverAddress) -> *mut c_char {
+    with_address(address, |address| {
+        Some(address.transport.as_str().to_string())
+    })
+}
+
+/// The IP, hostname, socket path or other transport data.
+///
+/// # Safety
+///
+/// `address` must be a live address from `msaddr_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn msaddr_host(address: *const MultiserverAddress) -> *mut c_char {
+    with_address(address, |address| Some(address.host_text()))
+}
+
+/// The port, or 0 for transports without one.
+///
+/// # Safety
+///
+/// `address` must be a live address from `msaddr_parse` or null.
+#[no_mangle]
+pub unsafe extern "C" fn msaddr_port(address: *const MultiserverAddress) -> u16 {
+    address.as_ref().map_or(0, |address| address.port)
+}
+
+/// The `@…=.ed25519` feed id of the `shs` key, or null without one.
+///
+/// # Safety
+///
+/// `address` must be a live address from `msaddr_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn msaddr_key(address: *const MultiserverAddress) -> *mut c_char {
+    with_address(address, MultiserverAddress::feed_id)
+}
+
+/// The address in its multiserver string form.
+///
+/// # Safety
+///
+/// `address` must be a live address from `msaddr_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn msaddr_to_string(address: *const MultiserverAddress) -> *mut c_char {
+    with_address(address, |address| Some(address.to_string()))
+}
+
+/// Releases a string returned by one of the accessors. Null is ignored.
+///
+/// # Safety
+///
+/// `st` must come from this library and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn msaddr_string_free(st: *mut c_char) {
+    if !st.is_null() {
+        drop(CString::from_raw(st));
+    }
+}
+
+// Null for a null address, a missing value, or a value with an interior NUL.
+unsafe fn with_address<F>(address: *const MultiserverAddress, f: F) -> *mut c_char
+where
+    F: FnOnce(&MultiserverAddress) -> Option<String>,
+{
+    address
+        .as_ref()
+        .and_then(f)
+        .and_then(|st| CString::new(st).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn take(st: *mut c_char) -> String {
+        let owned = CStr::from_ptr(st).to_str().unwrap().to_string();
+        msaddr_string_free(st);
+        owned
+    }
+
+    #[test]
+    fn ffi_parse_ok() {
+        let input =
+            CString::new("net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=")
+                .unwrap();
+        let mut address = ptr::null_mut();
+        unsafe {
+            assert_eq!(msaddr_parse(input.as_ptr(), &mut address), MSADDR_OK);
+            assert_eq!(take(msaddr_transport(address)), "net");
+            assert_eq!(take(msaddr_host(address)), "host.com");
+            assert_eq!(msaddr_port(address), 8008);
+            assert_eq!(
+                take(msaddr_key(address)),
+                "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+            );
+            assert_eq!(take(msaddr_to_string(address)), input.to_str().unwrap());
+            msaddr_free(address);
+        }
+    }
+
+    #[test]
+    fn ffi_parse_err() {
+        let input = CString::new("not an address").unwrap();
+        let mut address = ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                msaddr_parse(input.as_ptr(), &mut address),
+                MSADDR_ERR_SYNTAX
+            );
+            assert!(address.is_null());
+            assert_eq!(
+                msaddr_parse(ptr::null(), &mut address),
+                MSADDR_ERR_INVALID_ARGUMENT
+            );
+            assert!(msaddr_key(ptr::null()).is_null());
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{AddressType, MultiserverAddress};
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+}
+
+/// Implemented by callers over whatever database or service they have (a
+/// maxminddb reader, an HTTP API), so this crate stays free of GeoIP data.
+pub trait GeoResolver {
+    fn resolve(&self, ip: IpAddr) -> Option<GeoInfo>;
+}
+
+impl<F> GeoResolver for F
+where
+    F: Fn(IpAddr) -> Option<GeoInfo>,
+{
+    fn resolve(&self, ip: IpAddr) -> Option<GeoInfo> {
+        self(ip)
+    }
+}
+
+/// Only IP-based addresses are classified; hostnames would need a DNS lookup.
+pub fn classify_geo<R: GeoResolver + ?Sized>(
+    address: &MultiserverAddress,
+    resolver: &R,
+) -> Option<GeoInfo> {
+    match address.address {
+        AddressType::Ip(ip) => resolver.resolve(ip),
+        _ => None,
+    }
+}
+
+pub fn classify_geo_all<'a, R: GeoResolver + ?Sized>(
+    addresses: &'a [MultiserverAddress],
+    resolver: &R,
+) -> Vec<(&'a MultiserverAddress, Option<GeoInfo>)> {
+    addresses
+        .iter()
+        .map(|address| (address, classify_geo(address, resolver)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn classify_geo_ok() {
+        let resolver = |_ip: IpAddr| {
+            Some(GeoInfo {
+                country: Some("DE".to_string()),
+                asn: Some(3320),
+            })
+        };
+        let ip = MultiserverAddress::from_str(
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let host = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+
+        assert_eq!(
+            classify_geo(&ip, &resolver).unwrap().country.as_deref(),
+            Some("DE")
+        );
+        assert_eq!(classify_geo(&host, &resolver), None);
+        assert_eq!(classify_geo_all(&[ip, host], &resolver).len(), 2);
+    }
+}
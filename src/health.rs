@@ -0,0 +1,110 @@
+use crate::list::MultiserverAddressList;
+use crate::MultiserverAddress;
+use std::io;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+/// How long `check` waits for each address before reporting a timeout.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why an address failed its health check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HealthError {
+    Refused,
+    Timeout,
+    /// `connect` cannot dial this transport.
+    Unsupported,
+    /// Resolution or routing failures and anything else.
+    Unreachable,
+}
+
+impl From<&io::Error> for HealthError {
+    fn from(err: &io::Error) -> HealthError {
+        match err.kind() {
+            io::ErrorKind::ConnectionRefused => HealthError::Refused,
+            io::ErrorKind::TimedOut => HealthError::Timeout,
+            io::ErrorKind::Unsupported => HealthError::Unsupported,
+            _ => HealthError::Unreachable,
+        }
+    }
+}
+
+/// The outcome of connecting to one address: the time until the transport
+/// was open, or why it could not be opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    pub address: MultiserverAddress,
+    pub latency: Result<Duration, HealthError>,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.latency.is_ok()
+    }
+}
+
+/// Tries every alternative of `addresses` in order with `CHECK_TIMEOUT`
+/// each, and reports one result per address in the same order.
+pub async fn check(addresses: &MultiserverAddressList) -> Vec<HealthReport> {
+    check_with_timeout(addresses, CHECK_TIMEOUT).await
+}
+
+/// Like `check`, with a timeout of `limit` per address. Only the transport
+/// is opened; the secret handshake is left to the caller.
+pub async fn check_with_timeout(
+    addresses: &MultiserverAddressList,
+    limit: Duration,
+) -> Vec<HealthReport> {
+    let mut reports = Vec::with_capacity(addresses.len());
+    for address in addresses.iter() {
+        let start = Instant::now();
+        let latency = match timeout(limit, address.connect()).await {
+            Ok(Ok(_)) => Ok(start.elapsed()),
+            Ok(Err(err)) => Err(HealthError::from(&err)),
+            Err(_) => Err(HealthError::Timeout),
+        };
+        reports.push(HealthReport {
+            address: address.clone(),
+            latency,
+        });
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::str::FromStr;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn check_reports_ok() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap().port();
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addresses = MultiserverAddressList::from_str(&format!(
+            "net:127.0.0.1:{}~noauth;net:127.0.0.1:{}~noauth;bt:0123456789ab~noauth",
+            open, closed
+        ))
+        .unwrap();
+
+        let reports = block_on(check(&addresses));
+        assert_eq!(reports.len(), 3);
+        assert!(reports[0].is_healthy());
+        assert_eq!(reports[1].latency, Err(HealthError::Refused));
+        assert_eq!(reports[2].latency, Err(HealthError::Unsupported));
+        assert_eq!(reports[2].address, addresses[2]);
+    }
+}
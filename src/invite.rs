@@ -0,0 +1,189 @@
+use crate::{
+    AddressType, Error, InviteSeedInvalid, InviteTransportInvalid, MultiserverAddress,
+    NoPubKeyString, SecurityLayer, Transport,
+};
+use base64::{decode, encode};
+use rand::RngCore;
+use snafu::{ensure, OptionExt};
+use ssb_multiformats::multikey::Multikey;
+use std::fmt;
+use std::str::FromStr;
+
+/// A classic pub invite, displayed in the multiserver form it is parsed from,
+/// `net:host:port~shs:<key>:<seed>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Invite {
+    pub address: AddressType,
+    pub port: u16,
+    pub pub_key: Multikey,
+    pub seed: [u8; 32],
+}
+
+impl Invite {
+    /// The key of `address` is ignored in favour of `pub_key`, the key of the
+    /// pub that will redeem the invite. Pubs redeem invites over `net` only,
+    /// so other transports are rejected.
+    pub fn new(
+        address: &MultiserverAddress,
+        pub_key: Multikey,
+        seed: [u8; 32],
+    ) -> Result<Invite, Error> {
+        ensure!(
+            address.transport == Transport::Net,
+            InviteTransportInvalid {
+                transport: address.transport.clone()
+            }
+        );
+        Ok(Invite {
+            address: address.address.clone(),
+            port: address.port,
+            pub_key,
+            seed,
+        })
+    }
+
+    pub fn generate(address: &MultiserverAddress, pub_key: Multikey) -> Result<Invite, Error> {
+        let mut seed = [0; 32];
+        rand::thread_rng().fill_bytes(&mut seed);
+        Invite::new(address, pub_key, seed)
+    }
+
+    /// The legacy `host:port:@key.ed25519~seed` form older clients accept.
+    pub fn to_legacy_string(&self) -> String {
+        let host = match &self.address {
+            AddressType::Ip(ip) => ip.to_string(),
+            AddressType::Hostname(host) => host.clone(),
+            // `new` only accepts `net` addresses, which have one of the above.
+            _ => String::new(),
+        };
+        format!(
+            "{}:{}:{}~{}",
+            host,
+            self.port,
+            self.pub_key.to_legacy_string(),
+            encode(self.seed)
+        )
+    }
+
+    /// The `net` address of the pub that redeems the invite.
+    pub fn address(&self) -> MultiserverAddress {
+        MultiserverAddress {
+            transport: Transport::Net,
+            port: self.port,
+            address: self.address.clone(),
+            path: None,
+            security: vec![SecurityLayer::Shs(self.pub_key.clone())],
+        }
+    }
+}
+
+/// Parses the multiserver invite form, where the seed follows the key in
+/// the `shs` layer: `net:host:8008~shs:<key>:<seed>`.
+impl FromStr for Invite {
+    type Err = Error;
+
+    fn from_str(st: &str) -> Result<Invite, Error> {
+        let key_start = st.find("~shs:").context(NoPubKeyString)? + "~shs:".len();
+        let layer_end = st[key_start..]
+            .find('~')
+            .map_or(st.len(), |i| key_start + i);
+        let seed_start = key_start
+            + st[key_start..layer_end]
+                .find(':')
+                .context(InviteSeedInvalid)?;
+
+        let address = format!("{}{}", &st[..seed_start], &st[layer_end..]);
+        let address = MultiserverAddress::from_str(&address)?;
+        let pub_key = address.pub_key().cloned().context(NoPubKeyString)?;
+
+        let seed_vec = decode(&st[seed_start + 1..layer_end])
+            .ok()
+            .context(InviteSeedInvalid)?;
+        let mut seed = [0; 32];
+        if seed_vec.len() != seed.len() {
+            return InviteSeedInvalid.fail();
+        }
+        seed.copy_from_slice(&seed_vec);
+
+        Invite::new(&address, pub_key, seed)
+    }
+}
+
+// The seed goes last in the `shs` layer, which is the last layer since
+// invite addresses have no `noauth`.
+impl fmt::Display for Invite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.address(), encode(self.seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn invite_display_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let pub_key = address.pub_key().cloned().unwrap();
+        let invite = Invite::new(&address, pub_key, [0; 32]).unwrap();
+        assert_eq!(
+            invite.to_string(),
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+        );
+        assert_eq!(Invite::from_str(&invite.to_string()).unwrap(), invite);
+        assert_eq!(
+            invite.to_legacy_string(),
+            "host.com:8008:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519~AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+        );
+    }
+    #[test]
+    fn invite_generate_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let pub_key = address.pub_key().cloned().unwrap();
+        let a = Invite::generate(&address, pub_key.clone()).unwrap();
+        let b = Invite::generate(&address, pub_key);
+        assert_ne!(a.seed, b.unwrap().seed);
+        assert!(a.to_string().starts_with("net:192.168.178.17:8008~shs:"));
+    }
+    #[test]
+    fn invite_parse_ok() {
+        let invite = Invite::from_str("net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=:AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=").unwrap();
+        assert_eq!(invite.port, 8008);
+        assert_eq!(invite.seed, [1; 32]);
+        assert_eq!(
+            invite.pub_key.to_legacy_string(),
+            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        );
+
+        match Invite::from_str("net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=")
+        {
+            Err(Error::InviteSeedInvalid {}) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+    #[test]
+    fn invite_transport_err() {
+        let address = MultiserverAddress::from_str(
+            "wss:host.com~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let pub_key = address.pub_key().cloned().unwrap();
+        match Invite::new(&address, pub_key, [0; 32]) {
+            Err(Error::InviteTransportInvalid { transport }) => {
+                assert_eq!(transport, Transport::Wss)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match Invite::from_str("unix:/tmp/s~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=:AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=") {
+            Err(Error::InviteTransportInvalid { .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    address_from_host, legacy_key, AddressType, Error, MultiserverAddress, NoPortString,
+    NoPubKeyString, PortNotNumeric, SecurityLayer, Transport,
+};
+use snafu::{OptionExt, ResultExt};
+use ssb_multiformats::multikey::Multikey;
+use std::str::FromStr;
+
+/// The pre-multiserver `host:port:@key.ed25519` format found in old configs
+/// and invites.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LegacyAddress {
+    pub address: AddressType,
+    pub port: u16,
+    pub pub_key: Multikey,
+}
+
+impl FromStr for LegacyAddress {
+    type Err = Error;
+
+    fn from_str(st: &str) -> Result<LegacyAddress, Error> {
+        let key_start = st.rfind(":@").context(NoPubKeyString)?;
+        let (host_port, pub_key_str) = (&st[..key_start], &st[key_start + 1..]);
+        let port_start = host_port.rfind(':').context(NoPortString)?;
+        let (host, port_str) = (&host_port[..port_start], &host_port[port_start + 1..]);
+
+        let pub_key = legacy_key(pub_key_str)?;
+
+        let port = u16::from_str(port_str).context(PortNotNumeric)?;
+
+        let address = address_from_host(host)?;
+
+        Ok(LegacyAddress {
+            address,
+            port,
+            pub_key,
+        })
+    }
+}
+
+impl From<LegacyAddress> for MultiserverAddress {
+    fn from(legacy: LegacyAddress) -> MultiserverAddress {
+        MultiserverAddress {
+            transport: Transport::Net,
+            address: legacy.address,
+            port: legacy.port,
+            path: None,
+            security: vec![SecurityLayer::Shs(legacy.pub_key)],
+        }
+    }
+}
+
+/// A peer record as used by ssb-server's gossip table and other JS tooling,
+/// e.g. `{"host":"1.2.3.4","port":8008,"key":"@…=.ed25519"}`.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct PeerObject {
+    pub host: String,
+    pub port: u16,
+    pub key: String,
+}
+
+#[cfg(feature = "serde")]
+impl MultiserverAddress {
+    /// A `net` address for `peer`. The key must be a legacy `@…=.ed25519` id.
+    pub fn from_peer_object(peer: &PeerObject) -> Result<MultiserverAddress, Error> {
+        Ok(MultiserverAddress::from(LegacyAddress {
+            address: address_from_host(&peer.host)?,
+            port: peer.port,
+            pub_key: legacy_key(&peer.key)?,
+        }))
+    }
+
+    /// The peer record for this address, or `None` unless it is a `net`
+    /// address with a known key and an IP or hostname host.
+    pub fn to_peer_object(&self) -> Option<PeerObject> {
+        let host = match &self.address {
+            AddressType::Ip(ip) => ip.to_string(),
+            AddressType::Hostname(host) => host.clone(),
+            _ => return None,
+        };
+        match (&self.transport, self.pub_key()) {
+            (Transport::Net, Some(pub_key)) => Some(PeerObject {
+                host,
+                port: self.port,
+                key: pub_key.to_legacy_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Converts the entries of a legacy `gossip.json` (objects with `host`,
+/// `port` and `key`) into a map keyed by multiserver address, as used by
+/// conn.json. `host` and `port` are folded into the address; `key` and every
+/// other field are kept as metadata.
+#[cfg(feature = "legacy-gossip")]
+pub fn migrate_gossip(
+    entries: &[serde_json::Value],
+) -> Result<serde_json::Map<String, serde_json::Value>, Error> {
+    let mut migrated = serde_json::Map::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let mut metadata = entry
+            .as_object()
+            .cloned()
+            .context(crate::GossipEntryInvalid { index })?;
+        let host = metadata.remove("host");
+        let port = metadata.remove("port");
+
+        let host = host.as_ref().and_then(|host| host.as_str());
+        let port = port.as_ref().and_then(|port| port.as_u64());
+        let key = metadata.get("key").and_then(|key| key.as_str());
+        let (host, port, key) = match (host, port, key) {
+            (Some(host), Some(port), Some(key)) => (host, port, key),
+            _ => return crate::GossipEntryInvalid { index }.fail(),
+        };
+
+        let legacy = LegacyAddress::from_str(&format!("{}:{}:{}", host, port, key))?;
+        let address = MultiserverAddress::from(legacy).to_string();
+
+        migrated.insert(address, serde_json::Value::Object(metadata));
+    }
+
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_parse_ok() {
+        let legacy = LegacyAddress::from_str(
+            "host.com:8008:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519",
+        )
+        .unwrap();
+        let address = MultiserverAddress::from(legacy);
+        assert_eq!(
+            address,
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        );
+    }
+    #[test]
+    fn legacy_parse_ipv6_ok() {
+        let legacy = LegacyAddress::from_str(
+            "fe80::1:8008:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519",
+        )
+        .unwrap();
+        assert_eq!(legacy.port, 8008);
+        match legacy.address {
+            AddressType::Ip(ip) => assert!(ip.is_ipv6()),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn legacy_parse_err() {
+        assert!(LegacyAddress::from_str("host.com:8008").is_err());
+        assert!(LegacyAddress::from_str("host.com:8008:@notakey").is_err());
+        assert!(LegacyAddress::from_str(
+            "host.com:port:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        )
+        .is_err());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn peer_object_ok() {
+        let peer: PeerObject = serde_json::from_str(
+            r#"{"host":"::1","port":8008,"key":"@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"}"#,
+        )
+        .unwrap();
+        let address = MultiserverAddress::from_peer_object(&peer).unwrap();
+        assert_eq!(
+            address,
+            "net:[::1]:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        );
+        assert_eq!(address.to_peer_object(), Some(peer));
+        assert_eq!(address.as_noauth().to_peer_object(), None);
+    }
+    #[cfg(feature = "legacy-gossip")]
+    #[test]
+    fn migrate_gossip_ok() {
+        let entries: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[{
+                "host": "host.com",
+                "port": 8008,
+                "key": "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519",
+                "source": "pub",
+                "announcers": 3
+            }]"#,
+        )
+        .unwrap();
+        let migrated = migrate_gossip(&entries).unwrap();
+        let metadata =
+            &migrated["net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="];
+        assert_eq!(metadata["source"], "pub");
+        assert_eq!(metadata["announcers"], 3);
+        assert!(metadata.get("host").is_none());
+    }
+    #[cfg(feature = "legacy-gossip")]
+    #[test]
+    fn migrate_gossip_err() {
+        let entries = vec![serde_json::json!({ "host": "host.com" })];
+        match migrate_gossip(&entries) {
+            Err(Error::GossipEntryInvalid { index: 0 }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -1,35 +1,332 @@
 use base64::{decode, DecodeError};
-use lazy_static::lazy_static;
-use regex::Regex;
-use snafu::{OptionExt, ResultExt, Snafu};
+use multiserver_address_rs_grammar::{
+    self as grammar, default_port, is_onion_v3, is_url_path, unescape, Limit, ADDRESS_RE,
+};
+use regex::Captures;
+use sha2::{Digest, Sha256};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
 use ssb_multiformats::multikey::Multikey;
-use std::net::{AddrParseError, IpAddr};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::net::{AddrParseError, IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
 use std::num::ParseIntError;
 use std::str::FromStr;
-use url::{ParseError, Url};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+#[cfg(feature = "url")]
+use url::{Host, ParseError, Url};
+
+// Lets `msaddr!` expansions name this crate from inside it too.
+extern crate self as multiserver_address_rs;
+
+pub mod annotated;
+#[cfg(feature = "anonymize")]
+mod anonymize;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+pub mod borrowed;
+pub mod builder;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub mod connect;
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+#[path = "connect_web.rs"]
+pub mod connect;
+#[cfg(feature = "async-std")]
+pub mod connect_async_std;
+mod diagnose;
+pub mod dial_cache;
+#[cfg(feature = "discovery")]
+pub mod discovery;
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod geo;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub mod health;
+pub mod invite;
+pub mod legacy;
+pub mod list;
+#[cfg(feature = "loader")]
+pub mod loader;
+pub mod matcher;
+pub mod pattern;
+#[cfg(feature = "peer-book")]
+pub mod peer_book;
+pub mod resolve;
+pub mod rewrite;
+pub mod scheduler;
+pub mod stack;
+pub mod stats;
+pub mod template;
+#[cfg(feature = "test-util")]
+pub mod testnet;
+#[cfg(feature = "url")]
+mod uri;
+mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// Parses an address literal at compile time; see the `macros` feature.
+#[cfg(feature = "macros")]
+pub use multiserver_address_rs_macros::msaddr;
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub use ssb_multiformats::multikey::Multikey as __Multikey;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AddressType {
-    Url(Url),
+    /// A DNS name in lowercase punycode.
+    Hostname(String),
     Ip(IpAddr),
     SocketFilePath(String),
+    /// Opaque signalling data of a `webrtc:` address.
+    WebRtc(String),
+    /// Bluetooth device address of a `bt:` address, as 12 hex digits.
+    Bluetooth(String),
+    /// Opaque invite channel of a `dht:` address.
+    Dht(String),
+    /// A room tunnel to `target` through the room identified by `portal`.
+    Tunnel {
+        #[cfg_attr(feature = "serde", serde(with = "legacy_key"))]
+        portal: Multikey,
+        #[cfg_attr(feature = "serde", serde(with = "legacy_key"))]
+        target: Multikey,
+    },
+}
+
+impl AddressType {
+    // Position in the declaration order, for comparing different kinds.
+    fn rank(&self) -> u8 {
+        match self {
+            AddressType::Hostname(_) => 0,
+            AddressType::Ip(_) => 1,
+            AddressType::SocketFilePath(_) => 2,
+            AddressType::WebRtc(_) => 3,
+            AddressType::Bluetooth(_) => 4,
+            AddressType::Dht(_) => 5,
+            AddressType::Tunnel { .. } => 6,
+        }
+    }
+
+    /// The hostname as the `tcp:///<host>` URL it used to be stored as.
+    #[cfg(feature = "url")]
+    #[deprecated(note = "hostnames are now stored as `AddressType::Hostname`")]
+    pub fn url(&self) -> Option<Url> {
+        match self {
+            AddressType::Hostname(host) => Url::parse(&format!("tcp:///{}", host)).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Transports order as declared here.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Transport {
+    Net,
+    Udp,
+    Http,
+    Https,
+    /// WebSocket, as exposed by rooms and browser-reachable pubs.
+    Ws,
+    Wss,
+    /// Tor hidden service; the host must be a v3 `.onion` name.
+    Onion,
+    /// Local-daemon socket, `unix:/path/to/socket`.
+    Unix,
+    /// Bluetooth replication, as used by Manyverse.
+    Bt,
+    /// `ssb-dht-invite` rendezvous over the DHT.
+    Dht,
+    /// Room-mediated connection, `tunnel:@portal.ed25519:@target.ed25519`.
+    Tunnel,
+    WebRtc,
+    /// Experimental QUIC transport. The optional certificate hash follows the
+    /// port and must start with a letter, e.g. `quic:host:443:sha256-…`.
+    /// Only parsed with the `quic` feature, but always defined so turning the
+    /// feature on cannot break exhaustive matches downstream.
+    Quic {
+        cert_hash: Option<String>,
+    },
+}
+
+impl Transport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Transport::Net => "net",
+            Transport::Udp => "udp",
+            Transport::Http => "http",
+            Transport::Https => "https",
+            Transport::Ws => "ws",
+            Transport::Wss => "wss",
+            Transport::Onion => "onion",
+            Transport::Unix => "unix",
+            Transport::Bt => "bt",
+            Transport::Dht => "dht",
+            Transport::Tunnel => "tunnel",
+            Transport::WebRtc => "webrtc",
+            Transport::Quic { .. } => "quic",
+        }
+    }
+
+    /// The well-known port of web transports, which may be left out of an
+    /// address: 80 for `ws` and `http`, 443 for `wss` and `https`.
+    pub fn default_port(&self) -> Option<u16> {
+        default_port(self.as_str())
+    }
+
+    /// Whether addresses on this transport may carry a request path, as
+    /// rooms behind reverse proxies do with `wss:host:443/ssb-ws`.
+    pub fn allows_path(&self) -> bool {
+        grammar::allows_path(self.as_str())
+    }
+
+    /// Whether addresses on this transport are a `host:port` pair, rather
+    /// than a socket path, device address, key pair or opaque data.
+    pub fn has_host_port(&self) -> bool {
+        !matches!(
+            self,
+            Transport::Unix
+                | Transport::Bt
+                | Transport::Dht
+                | Transport::Tunnel
+                | Transport::WebRtc
+        )
+    }
+}
+
+/// Kinds of host order as declared; hosts of the same kind order by their
+/// value, with tunnel keys compared as bytes.
+impl Ord for AddressType {
+    fn cmp(&self, other: &AddressType) -> Ordering {
+        use AddressType::*;
+        match (self, other) {
+            (Hostname(a), Hostname(b))
+            | (SocketFilePath(a), SocketFilePath(b))
+            | (WebRtc(a), WebRtc(b))
+            | (Bluetooth(a), Bluetooth(b))
+            | (Dht(a), Dht(b)) => a.cmp(b),
+            (Ip(a), Ip(b)) => a.cmp(b),
+            (
+                Tunnel { portal, target },
+                Tunnel {
+                    portal: other_portal,
+                    target: other_target,
+                },
+            ) => (portal.to_legacy_vec(), target.to_legacy_vec())
+                .cmp(&(other_portal.to_legacy_vec(), other_target.to_legacy_vec())),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for AddressType {
+    fn partial_cmp(&self, other: &AddressType) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Where a peer can be reached from, as returned by
+/// `MultiserverAddress::scope`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Loopback addresses and local sockets.
+    Device,
+    /// Private and link-local ranges, `.local` and single-label hostnames.
+    Local,
+    Public,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Device => "device",
+            Scope::Local => "local",
+            Scope::Public => "public",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MultiserverAddress {
-    pub pub_key: Option<Multikey>,
+    pub transport: Transport,
     pub port: u16,
     pub address: AddressType,
+    /// Request path of `http`, `https`, `ws` and `wss` addresses, starting
+    /// with `/`.
+    pub path: Option<String>,
+    /// The layers after the transport, in written order: at most one `Shs`,
+    /// then at most one `Noauth`. A trailing `Noauth` means the connection
+    /// is not authenticated even if the key is known.
+    pub security: Vec<SecurityLayer>,
+}
+
+/// One security layer of an address, in the order it is written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SecurityLayer {
+    Shs(#[cfg_attr(feature = "serde", serde(with = "legacy_key"))] Multikey),
+    Noauth,
+}
+
+impl SecurityLayer {
+    /// The layers for an optional `shs` key and an optional `~noauth`, in
+    /// the order they are written.
+    pub fn layers(pub_key: Option<Multikey>, noauth: bool) -> Vec<SecurityLayer> {
+        let noauth = Some(SecurityLayer::Noauth).filter(|_| noauth);
+        pub_key
+            .map(SecurityLayer::Shs)
+            .into_iter()
+            .chain(noauth)
+            .collect()
+    }
 }
 
+/// Errors from parsing and building addresses. New variants may be added in
+/// minor releases; use `kind()` for coarse matching.
 #[derive(Debug, Snafu)]
+#[non_exhaustive]
 pub enum Error {
-    #[snafu(display("Could not parse address"))]
-    Parse {},
+    #[snafu(display("Unknown or disabled protocol {}", name))]
+    ProtocolUnknown { name: String },
+    #[snafu(display("{} addresses cannot have a {}", transport.as_str(), field))]
+    FieldNotAllowed {
+        transport: Transport,
+        field: &'static str,
+    },
+    #[snafu(display("Path must start with `/` and hold only URL path characters"))]
+    PathInvalid {},
+    #[snafu(display("Host does not fit the {} transport", transport.as_str()))]
+    HostInvalid { transport: Transport },
+    #[snafu(display("Input holds {} addresses, expected 1", count))]
+    AddressCount { count: usize },
+    #[snafu(display("Address has {} transport layers, expected at most 1", count))]
+    TransportLayerCount { count: usize },
+    /// The input did not match the address grammar. `offset` is the byte at
+    /// which the `segment` being parsed stopped matching.
+    #[snafu(display("{}", diagnose::render(input, *offset, segment, expected)))]
+    Unexpected {
+        input: String,
+        offset: usize,
+        segment: &'static str,
+        expected: &'static str,
+    },
+    #[snafu(display("Address is not in canonical form {}", canonical))]
+    NotCanonical { canonical: String },
     #[snafu(display("Could parse ip"))]
     IpInvalid { source: AddrParseError },
+    #[cfg(feature = "url")]
     #[snafu(display("Could parse url"))]
     UrlInvalid { source: ParseError },
+    /// Without the `url` feature, hostnames are limited to ASCII letters,
+    /// digits, `-`, `_` and `.`.
+    #[snafu(display("{} is not a valid hostname", host))]
+    HostnameInvalid { host: String },
     #[snafu(display("Port was not numeric"))]
     PortNotNumeric { source: ParseIntError },
     #[snafu(display("Could not find network address in string"))]
@@ -44,142 +341,2599 @@ pub enum Error {
     NoPortString {},
     #[snafu(display("Could not decode pubkey as base64"))]
     PubKeyNotBase64 { source: DecodeError },
+    #[snafu(display("Address string is {} bytes, limit is {}", length, MAX_ADDRESS_LEN))]
+    InputTooLong { length: usize },
+    #[snafu(display("Address string has {} segments, limit is {}", count, MAX_SEGMENTS))]
+    TooManySegments { count: usize },
+    #[snafu(display("Could not parse CIDR range"))]
+    CidrInvalid {},
+    #[snafu(display("Could not parse address pattern"))]
+    PatternInvalid {},
+    #[snafu(display("Could not parse address template"))]
+    TemplateInvalid {},
+    #[snafu(display("Could not decode legacy pub key"))]
+    LegacyPubKeyInvalid {},
+    #[snafu(display("Gossip entry {} is missing host, port or key", index))]
+    GossipEntryInvalid { index: usize },
+    #[snafu(display("Protocol segment at byte {} has no name", offset))]
+    SegmentNameMissing { offset: usize },
+    #[snafu(display("Control character at byte {}", offset))]
+    ControlCharacter { offset: usize },
+    #[snafu(display("Non-ASCII character at byte {}", offset))]
+    NonAscii { offset: usize },
+    #[snafu(display("Address {} in list is invalid: {}", index, source))]
+    ListEntryInvalid { index: usize, source: Box<Error> },
+    #[snafu(display("Pub key is {} bytes, expected 32", actual))]
+    PubKeyWrongLength { actual: usize },
+    #[snafu(display("Unknown key algorithm {}", algorithm))]
+    KeyAlgorithmUnknown { algorithm: String },
+    #[snafu(display("{} test nodes do not fit in the port range", nodes))]
+    TestnetTooLarge { nodes: u16 },
+    #[snafu(display("Invite seed is missing or not 32 bytes of base64"))]
+    InviteSeedInvalid {},
+    #[snafu(display("Invites are redeemed over net, not {}", transport.as_str()))]
+    InviteTransportInvalid { transport: Transport },
+    #[snafu(display("Not an SSB URI with an msaddr parameter"))]
+    SsbUriInvalid {},
+    #[snafu(display("{} is not a v3 onion address", host))]
+    OnionInvalid { host: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The input does not have the shape of a multiserver address.
+    Syntax,
+    /// The shape is right but a field holds an invalid value.
+    Semantic,
+    /// The input exceeded one of the parser's resource limits.
+    Limit,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::ProtocolUnknown { .. }
+            | Error::FieldNotAllowed { .. }
+            | Error::PathInvalid {}
+            | Error::AddressCount { .. }
+            | Error::TransportLayerCount { .. }
+            | Error::NoAddressString {}
+            | Error::NoIpString {}
+            | Error::NoUrlString {}
+            | Error::NoPubKeyString {}
+            | Error::NoPortString {}
+            | Error::CidrInvalid {}
+            | Error::PatternInvalid {}
+            | Error::TemplateInvalid {}
+            | Error::GossipEntryInvalid { .. }
+            | Error::SegmentNameMissing { .. }
+            | Error::ControlCharacter { .. }
+            | Error::NonAscii { .. }
+            | Error::Unexpected { .. }
+            | Error::SsbUriInvalid {}
+            | Error::NotCanonical { .. } => ErrorKind::Syntax,
+            Error::IpInvalid { .. }
+            | Error::HostnameInvalid { .. }
+            | Error::PortNotNumeric { .. }
+            | Error::PubKeyNotBase64 { .. }
+            | Error::LegacyPubKeyInvalid {}
+            | Error::PubKeyWrongLength { .. }
+            | Error::HostInvalid { .. }
+            | Error::KeyAlgorithmUnknown { .. }
+            | Error::InviteSeedInvalid {}
+            | Error::InviteTransportInvalid { .. }
+            | Error::OnionInvalid { .. } => ErrorKind::Semantic,
+            #[cfg(feature = "url")]
+            Error::UrlInvalid { .. } => ErrorKind::Semantic,
+            Error::InputTooLong { .. }
+            | Error::TooManySegments { .. }
+            | Error::TestnetTooLarge { .. } => ErrorKind::Limit,
+            Error::ListEntryInvalid { source, .. } => source.kind(),
+        }
+    }
+
+    pub fn is_syntax(&self) -> bool {
+        self.kind() == ErrorKind::Syntax
+    }
+
+    pub fn is_semantic(&self) -> bool {
+        self.kind() == ErrorKind::Semantic
+    }
+
+    pub fn is_limit(&self) -> bool {
+        self.kind() == ErrorKind::Limit
+    }
 }
 
+pub use multiserver_address_rs_grammar::{MAX_ADDRESS_LEN, MAX_SEGMENTS};
+
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+fn check_limits(st: &str) -> Result<()> {
+    grammar::check_limits(st).map_err(|limit| match limit {
+        Limit::Length { length } => Error::InputTooLong { length },
+        Limit::Segments { count } => Error::TooManySegments { count },
+        Limit::ControlCharacter { offset } => Error::ControlCharacter { offset },
+    })
+}
+
 impl FromStr for MultiserverAddress {
     type Err = Error;
 
     fn from_str(st: &str) -> Result<MultiserverAddress> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r"net:((?P<ipv4>\d+.\d+.\d+.\d+)|(?P<ipv6>.+:.+:.+:.+:.+:.+:.+:.+)|(?P<url>.+)):(?P<port>\d+)~\w+:(?P<pub_key>.+=)").unwrap();
+        MultiserverAddress::parse_without_key(st)?.decode()
+    }
+}
+
+/// An address parsed by `MultiserverAddress::parse_without_key`, with its
+/// `shs` key matched but not yet decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UndecodedAddress<'a> {
+    /// The address without its `shs` layer.
+    pub address: MultiserverAddress,
+    /// The text after `shs:`, if any.
+    pub pub_key: Option<&'a str>,
+}
+
+impl UndecodedAddress<'_> {
+    /// Decodes the key into the `shs` layer, failing as `from_str` would.
+    pub fn decode(self) -> Result<MultiserverAddress> {
+        let mut address = self.address;
+        if let Some(pub_key) = self.pub_key {
+            address
+                .security
+                .insert(0, SecurityLayer::Shs(shs_key(pub_key)?));
         }
-        let caps = RE.captures(st).context(Parse)?;
+        Ok(address)
+    }
+}
 
-        let ip_str = caps.name("ipv4").or_else(|| caps.name("ipv6"));
+impl MultiserverAddress {
+    /// Parses everything but the `shs` key, which is located but not
+    /// decoded. Decoding the key dominates parse time, so bulk processing
+    /// that only needs hosts and ports can skip it and call `decode` on the
+    /// few addresses it keeps.
+    pub fn parse_without_key(st: &str) -> Result<UndecodedAddress<'_>> {
+        check_limits(st)?;
 
-        let url_str = caps.name("url");
+        let caps = ADDRESS_RE
+            .captures(st)
+            .ok_or_else(|| diagnose::diagnose(st))?;
 
-        let pub_key_str = caps.name("pub_key").context(NoPubKeyString)?.as_str();
-        let port_str = caps.name("port").context(NoPortString)?.as_str();
+        let undecoded = caps.name("pub_key").map(|pub_key| pub_key.as_str());
+        let noauth = caps.name("noauth").is_some();
 
-        let pub_key_vec = decode(pub_key_str).context(PubKeyNotBase64)?;
-        let pub_key_bytes = array_32_from_vec(pub_key_vec);
+        if let Some((transport, address)) = portless_address(&caps)? {
+            return Ok(UndecodedAddress {
+                address: MultiserverAddress {
+                    transport,
+                    address,
+                    port: 0,
+                    path: None,
+                    security: SecurityLayer::layers(None, noauth),
+                },
+                pub_key: undecoded,
+            });
+        }
+
+        let cert_hash = caps.name("cert_hash").map(|hash| hash.as_str().to_string());
+        let name = caps
+            .name("transport")
+            .or_else(|| caps.name("web"))
+            .context(NoAddressString)?
+            .as_str();
+        let transport = match name {
+            "net" => Transport::Net,
+            "udp" => Transport::Udp,
+            "http" => Transport::Http,
+            "https" => Transport::Https,
+            "ws" => Transport::Ws,
+            "wss" => Transport::Wss,
+            "onion" => Transport::Onion,
+            #[cfg(feature = "quic")]
+            "quic" => Transport::Quic {
+                cert_hash: cert_hash.clone(),
+            },
+            _ => return ProtocolUnknown { name }.fail(),
+        };
+        let path = caps
+            .name("path")
+            .or_else(|| caps.name("web_path"))
+            .map(|path| path.as_str().to_string());
+        ensure!(
+            transport.allows_path() || path.is_none(),
+            FieldNotAllowed {
+                transport: transport.clone(),
+                field: "path"
+            }
+        );
+        ensure!(path.as_deref().is_none_or(is_url_path), PathInvalid);
+        ensure!(
+            transport.as_str() == "quic" || cert_hash.is_none(),
+            FieldNotAllowed {
+                transport: transport.clone(),
+                field: "certificate hash"
+            }
+        );
+
+        let ip_str = caps
+            .name("ipv4")
+            .or_else(|| caps.name("ipv6"))
+            .or_else(|| caps.name("ipv6_bracketed"));
 
-        let pub_key = Multikey::from_ed25519(&pub_key_bytes);
+        let url_str = caps.name("url").or_else(|| caps.name("web_host"));
 
         let address = match (ip_str, url_str) {
             (Some(ip_str), None) => IpAddr::from_str(ip_str.as_str())
                 .map(AddressType::Ip)
                 .context(IpInvalid),
-            (None, Some(url_str)) => {
-                let options = Url::options();
-                let tcp_base = Url::parse("tcp://").unwrap();
-                let base_url = options.base_url(Some(&tcp_base));
-                base_url
-                    .parse(url_str.as_str())
-                    .map(AddressType::Url)
-                    .context(UrlInvalid)
-            }
+            (None, Some(url_str)) => address_from_host(url_str.as_str()),
             _ => return Err(Error::NoAddressString {}),
         }?;
 
-        let port = u16::from_str(port_str).context(PortNotNumeric)?;
+        let port = match caps.name("port") {
+            Some(port_str) => u16::from_str(port_str.as_str()).context(PortNotNumeric)?,
+            None => transport.default_port().context(NoPortString)?,
+        };
 
-        Ok(MultiserverAddress {
-            address,
-            port,
-            pub_key: Some(pub_key),
+        check_onion(&transport, &address)?;
+
+        Ok(UndecodedAddress {
+            address: MultiserverAddress {
+                transport,
+                address,
+                port,
+                path,
+                security: SecurityLayer::layers(None, noauth),
+            },
+            pub_key: undecoded,
         })
     }
 }
 
-fn array_32_from_vec(vec: Vec<u8>) -> [u8; 32] {
-    let mut pub_key_bytes = [0; 32];
+// Transports whose data is not a `host:port` pair.
+fn portless_address(caps: &Captures) -> Result<Option<(Transport, AddressType)>> {
+    let text = |name| caps.name(name).map(|m| m.as_str());
+    Ok(Some(if let Some(socket) = text("socket") {
+        (
+            Transport::Unix,
+            AddressType::SocketFilePath(unescape(socket)),
+        )
+    } else if let Some(bt) = text("bt") {
+        (Transport::Bt, AddressType::Bluetooth(bt.to_string()))
+    } else if let Some(dht) = text("dht") {
+        (Transport::Dht, AddressType::Dht(unescape(dht)))
+    } else if let (Some(portal), Some(target)) = (text("portal"), text("target")) {
+        let portal = legacy_key(portal)?;
+        let target = legacy_key(target)?;
+        (Transport::Tunnel, AddressType::Tunnel { portal, target })
+    } else if let Some(signal) = text("signal") {
+        (Transport::WebRtc, AddressType::WebRtc(unescape(signal)))
+    } else {
+        return Ok(None);
+    }))
+}
 
-    vec.into_iter().enumerate().for_each(|(i, b)| {
-        pub_key_bytes[i] = b;
-    });
+/// How long `matches_socket_addr` trusts a cached hostname lookup.
+pub const RESOLVE_TTL: Duration = Duration::from_secs(300);
 
-    pub_key_bytes
+fn unmap_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        v4 => v4,
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::str::FromStr;
+/// How many hostname lookups `matches_socket_addr` keeps. Hostnames come
+/// from untrusted gossip, so the cache is bounded: expired entries are
+/// dropped first, then the least recently used.
+pub const RESOLVE_CACHE_CAPACITY: usize = 1024;
 
-    #[test]
-    fn multiserver_ipv4_parse_ok() {
-        let valid_ms_address =
-            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+// A lookup result with when it was resolved and when it was last used.
+struct Resolved {
+    at: Instant,
+    used: Instant,
+    ips: Vec<IpAddr>,
+}
 
-        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
-        assert_eq!(address.port, 8008);
-        assert_eq!(
-            address.pub_key.unwrap().to_legacy_string(),
-            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
-        );
-        match address.address {
-            AddressType::Ip(add) => assert!(add.is_ipv4()),
-            _ => panic!(),
+// Failed lookups are cached as empty so an unreachable resolver is not
+// hammered once per incoming connection.
+fn resolve_cached(host: &str) -> Vec<IpAddr> {
+    static RESOLVED: LazyLock<Mutex<HashMap<String, Resolved>>> = LazyLock::new(Default::default);
+    let now = Instant::now();
+    if let Some(resolved) = RESOLVED.lock().unwrap().get_mut(host) {
+        if now.duration_since(resolved.at) < RESOLVE_TTL {
+            resolved.used = now;
+            return resolved.ips.clone();
         }
     }
-    #[test]
-    fn multiserver_ipv6_1_parse_ok() {
-        let valid_ms_address = "net:1200:0000:AB00:1234:0000:2552:7777:1313:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
-        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
-        assert_eq!(address.port, 8008);
-        assert_eq!(
-            address.pub_key.unwrap().to_legacy_string(),
-            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
-        );
-        match address.address {
-            AddressType::Ip(add) => assert!(add.is_ipv6()),
-            _ => panic!(),
+    let ips: Vec<IpAddr> = (host, 0)
+        .to_socket_addrs()
+        .map(|addrs| addrs.map(|addr| addr.ip()).collect())
+        .unwrap_or_default();
+
+    let mut resolved = RESOLVED.lock().unwrap();
+    make_room(&mut resolved, host, now, RESOLVE_CACHE_CAPACITY);
+    resolved.insert(
+        host.to_string(),
+        Resolved {
+            at: now,
+            used: now,
+            ips: ips.clone(),
+        },
+    );
+    ips
+}
+
+// Frees a slot for `host` in a cache holding at most `capacity` entries.
+fn make_room(resolved: &mut HashMap<String, Resolved>, host: &str, now: Instant, capacity: usize) {
+    if resolved.contains_key(host) || resolved.len() < capacity {
+        return;
+    }
+    resolved.retain(|_, entry| now.duration_since(entry.at) < RESOLVE_TTL);
+    if resolved.len() < capacity {
+        return;
+    }
+    let least_recent = resolved
+        .iter()
+        .min_by_key(|(_, entry)| entry.used)
+        .map(|(host, _)| host.clone());
+    if let Some(least_recent) = least_recent {
+        resolved.remove(&least_recent);
+    }
+}
+
+// Version tag mixed into `stable_hash`. Bump it whenever the hashed form
+// changes so persisted keys from older versions never collide with new ones.
+const STABLE_HASH_VERSION: u8 = 3;
+
+impl MultiserverAddress {
+    /// Like `from_str`, but also rejects any non-ASCII input, for deployments
+    /// that never expect internationalized hosts.
+    pub fn from_ascii_str(st: &str) -> Result<MultiserverAddress> {
+        if let Some((offset, _)) = st.char_indices().find(|(_, c)| !c.is_ascii()) {
+            return NonAscii { offset }.fail();
         }
+        MultiserverAddress::from_str(st)
     }
-    #[test]
-    fn multiserver_ipv6_2_parse_ok() {
-        let valid_ms_address = "net:21DA:D3:0:2F3B:2AA:FF:FE28:9C5A:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
-        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
-        assert_eq!(address.port, 8008);
-        assert_eq!(
-            address.pub_key.unwrap().to_legacy_string(),
-            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
-        );
-        match address.address {
-            AddressType::Ip(add) => assert!(add.is_ipv6()),
-            _ => panic!(),
+
+    /// Accepts only the exact canonical form, as written by `Display`: IPv6
+    /// hosts compressed, hostnames in lowercase punycode and keys without an
+    /// `.ed25519` suffix.
+    pub fn parse_strict(st: &str) -> Result<MultiserverAddress> {
+        let address = MultiserverAddress::from_str(st)?;
+        let canonical = address.to_string();
+        ensure!(canonical == st, NotCanonical { canonical });
+        Ok(address)
+    }
+
+    /// Like `from_str`, but tolerates copy-paste noise: surrounding
+    /// whitespace, trailing semicolons and mixed-case protocol names.
+    pub fn parse_lenient(st: &str) -> Result<MultiserverAddress> {
+        let st = st.trim().trim_end_matches(';').trim_end();
+        let mut normalized = String::with_capacity(st.len());
+        for (i, (_, layer)) in split_unescaped(st, b'~').enumerate() {
+            if i > 0 {
+                normalized.push('~');
+            }
+            let name_end = layer.find(':').unwrap_or(layer.len());
+            normalized.push_str(&layer[..name_end].to_ascii_lowercase());
+            normalized.push_str(&layer[name_end..]);
         }
+        MultiserverAddress::from_str(&normalized)
     }
-    #[test]
-    fn multiserver_ipv6_3_parse_ok() {
-        let valid_ms_address = "net:FE80:0000:0000:0000:0202:B3FF:FE1E:8329:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
-        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
-        assert_eq!(address.port, 8008);
-        assert_eq!(
-            address.pub_key.unwrap().to_legacy_string(),
-            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
-        );
-        match address.address {
-            AddressType::Ip(add) => assert!(add.is_ipv6()),
-            _ => panic!(),
+
+    /// `net:127.0.0.1:<port>~shs:<pub_key>`.
+    pub fn loopback(port: u16, pub_key: Multikey) -> MultiserverAddress {
+        MultiserverAddress {
+            transport: Transport::Net,
+            address: AddressType::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            port,
+            path: None,
+            security: vec![SecurityLayer::Shs(pub_key)],
         }
     }
-    #[test]
-    fn multiserver_url_parse_ok() {
-        let valid_ms_address = "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
-        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
-        assert_eq!(address.port, 8008);
-        assert_eq!(
-            address.pub_key.unwrap().to_legacy_string(),
-            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
-        );
-        match address.address {
-            AddressType::Url(_url) => (),
-            _ => panic!(),
+
+    /// `tunnel:<portal>:<target>~shs:<shs_key>`, reaching `target` through
+    /// the room `portal`. `shs_key` is normally the target's key.
+    pub fn tunnel_via(portal: Multikey, target: Multikey, shs_key: Multikey) -> MultiserverAddress {
+        MultiserverAddress {
+            transport: Transport::Tunnel,
+            address: AddressType::Tunnel { portal, target },
+            port: 0,
+            path: None,
+            security: vec![SecurityLayer::Shs(shs_key)],
+        }
+    }
+
+    /// `net:localhost:<port>~noauth`, for unauthenticated local setups.
+    pub fn localhost(port: u16) -> MultiserverAddress {
+        MultiserverAddress {
+            transport: Transport::Net,
+            address: AddressType::Hostname("localhost".to_string()),
+            port,
+            path: None,
+            security: vec![SecurityLayer::Noauth],
+        }
+    }
+
+    /// Starts building an address; see `MultiserverAddressBuilder`.
+    pub fn builder() -> builder::MultiserverAddressBuilder {
+        builder::MultiserverAddressBuilder::default()
+    }
+
+    /// Copy of this address secured with `pub_key`, e.g. once a `localhost`
+    /// peer's key has been learned. All other fields are kept.
+    pub fn authenticated_with(&self, pub_key: Multikey) -> MultiserverAddress {
+        MultiserverAddress {
+            security: vec![SecurityLayer::Shs(pub_key)],
+            ..self.clone()
+        }
+    }
+
+    /// Copy of this address with its key replaced by a `~noauth` layer, for
+    /// local development.
+    pub fn as_noauth(&self) -> MultiserverAddress {
+        MultiserverAddress {
+            security: vec![SecurityLayer::Noauth],
+            ..self.clone()
+        }
+    }
+
+    /// The IP, hostname, socket path or other transport data as a plain,
+    /// unescaped string, for output that has no use for `AddressType`.
+    pub fn host_text(&self) -> String {
+        match &self.address {
+            AddressType::Hostname(host) => host.clone(),
+            AddressType::Ip(ip) => ip.to_string(),
+            AddressType::SocketFilePath(data)
+            | AddressType::WebRtc(data)
+            | AddressType::Bluetooth(data)
+            | AddressType::Dht(data) => data.clone(),
+            AddressType::Tunnel { portal, target } => format!(
+                "{}:{}",
+                portal.to_legacy_string(),
+                target.to_legacy_string()
+            ),
+        }
+    }
+
+    /// The raw ed25519 key, or `None` without a key or for other algorithms.
+    pub fn pub_key_bytes(&self) -> Option<[u8; 32]> {
+        if self.key_algorithm()? != "ed25519" {
+            return None;
+        }
+        let bytes = decode(self.pub_key_base64()?).ok()?;
+        array_32_from_vec(bytes).ok()
+    }
+
+    /// The key as the bare base64 written after `shs:`.
+    pub fn pub_key_base64(&self) -> Option<String> {
+        self.pub_key().map(pub_key_base64)
+    }
+
+    /// The key as an SSB feed id, e.g. `@HDOU…Qo4=.ed25519`.
+    pub fn feed_id(&self) -> Option<String> {
+        self.pub_key().map(Multikey::to_legacy_string)
+    }
+
+    /// The algorithm of the `shs` key, e.g. `ed25519`.
+    pub fn key_algorithm(&self) -> Option<String> {
+        let legacy = self.pub_key()?.to_legacy_string();
+        legacy.rfind('.').map(|dot| legacy[dot + 1..].to_string())
+    }
+
+    /// The `shs` and `noauth` layers of this address, in written order.
+    pub fn security_layers(&self) -> &[SecurityLayer] {
+        &self.security
+    }
+
+    /// The key of the `shs` layer, if any.
+    pub fn pub_key(&self) -> Option<&Multikey> {
+        self.security.iter().find_map(|layer| match layer {
+            SecurityLayer::Shs(pub_key) => Some(pub_key),
+            SecurityLayer::Noauth => None,
+        })
+    }
+
+    /// Whether the address has a `~noauth` layer.
+    pub fn is_noauth(&self) -> bool {
+        self.security.contains(&SecurityLayer::Noauth)
+    }
+
+    /// The normal form of this address: hostnames lowercased without a
+    /// trailing dot, IPv4-mapped IPv6 hosts as IPv4, and Bluetooth addresses
+    /// in lowercase hex. IPv6 spelling is already normalized by parsing.
+    pub fn canonicalize(&self) -> MultiserverAddress {
+        let address = match &self.address {
+            AddressType::Ip(ip) => AddressType::Ip(unmap_ip(*ip)),
+            AddressType::Hostname(host) => {
+                AddressType::Hostname(host.trim_end_matches('.').to_lowercase())
+            }
+            AddressType::Bluetooth(device) => AddressType::Bluetooth(device.to_lowercase()),
+            address => address.clone(),
         };
+        MultiserverAddress {
+            address,
+            ..self.clone()
+        }
+    }
+
+    /// Whether both addresses reach the same peer the same way, comparing
+    /// their canonical forms.
+    pub fn semantic_eq(&self, other: &MultiserverAddress) -> bool {
+        self.canonicalize() == other.canonicalize()
+    }
+
+    /// SHA-256 over `STABLE_HASH_VERSION` and the string form of the
+    /// `canonicalize`d address, so every spelling of an address hashes the
+    /// same. Unlike `Hash`, the result is identical across runs, platforms
+    /// and releases that share `STABLE_HASH_VERSION`.
+    pub fn stable_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.input([STABLE_HASH_VERSION]);
+        hasher.input(self.canonicalize().to_string().as_bytes());
+
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&hasher.result());
+        digest
+    }
+
+    /// `stable_hash` rendered as `v<version>:<hex>`, suitable as a database key.
+    pub fn cache_key(&self) -> String {
+        let hex: String = self
+            .stable_hash()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        format!("v{}:{}", STABLE_HASH_VERSION, hex)
+    }
+
+    /// Writes the `<transport>:<host>:<port>[path]~shs:<base64>` form
+    /// straight into `w`. IPv6 hosts are written in brackets so the port
+    /// cannot be mistaken for a group, and a transport's default port is
+    /// left out.
+    pub fn write_to<W: fmt::Write + ?Sized>(&self, w: &mut W) -> fmt::Result {
+        let transport = self.transport.as_str();
+        match &self.address {
+            AddressType::Ip(IpAddr::V4(ip)) => write!(w, "{}:{}", transport, ip)?,
+            AddressType::Ip(IpAddr::V6(ip)) => write!(w, "{}:[{}]", transport, ip)?,
+            AddressType::Hostname(host) => write!(w, "{}:{}", transport, host)?,
+            AddressType::SocketFilePath(path) => {
+                w.write_str("unix:")?;
+                write_escaped(w, path, &['!', ':', '~', ';'])?
+            }
+            AddressType::WebRtc(signal) => {
+                w.write_str("webrtc:")?;
+                write_escaped(w, signal, SEPARATORS)?
+            }
+            AddressType::Bluetooth(device) => write!(w, "bt:{}", device)?,
+            AddressType::Dht(channel) => {
+                w.write_str("dht:")?;
+                write_escaped(w, channel, SEPARATORS)?
+            }
+            AddressType::Tunnel { portal, target } => write!(
+                w,
+                "tunnel:{}:{}",
+                portal.to_legacy_string(),
+                target.to_legacy_string()
+            )?,
+        }
+        if matches!(self.address, AddressType::Ip(_) | AddressType::Hostname(_))
+            && self.transport.default_port() != Some(self.port)
+        {
+            write!(w, ":{}", self.port)?;
+        }
+        if let Transport::Quic {
+            cert_hash: Some(cert_hash),
+        } = &self.transport
+        {
+            write!(w, ":{}", cert_hash)?;
+        }
+        if let Some(path) = &self.path {
+            w.write_str(path)?;
+        }
+        for layer in &self.security {
+            match layer {
+                SecurityLayer::Shs(pub_key) => write!(w, "~shs:{}", shs_key_string(pub_key))?,
+                SecurityLayer::Noauth => w.write_str("~noauth")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The room a tunnel address goes through.
+    pub fn portal_key(&self) -> Option<&Multikey> {
+        match &self.address {
+            AddressType::Tunnel { portal, .. } => Some(portal),
+            _ => None,
+        }
+    }
+
+    /// The peer a tunnel address reaches through its portal.
+    pub fn target_key(&self) -> Option<&Multikey> {
+        match &self.address {
+            AddressType::Tunnel { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+
+    /// The hostname in its ASCII (punycode) form, as stored and used for DNS.
+    pub fn hostname(&self) -> Option<&str> {
+        match &self.address {
+            AddressType::Hostname(host) => Some(host),
+            _ => None,
+        }
+    }
+
+    /// The hostname with punycode labels decoded back to Unicode, for display.
+    #[cfg(feature = "url")]
+    pub fn hostname_unicode(&self) -> Option<String> {
+        self.hostname().map(|host| idna::domain_to_unicode(host).0)
+    }
+
+    /// Reachability scope in the sense of the JS `multiserver-scopes`.
+    pub fn scope(&self) -> Scope {
+        match &self.address {
+            AddressType::SocketFilePath(_) => Scope::Device,
+            AddressType::WebRtc(_) | AddressType::Dht(_) | AddressType::Tunnel { .. } => {
+                Scope::Public
+            }
+            AddressType::Bluetooth(_) => Scope::Local,
+            AddressType::Ip(ip) => match unmap_ip(*ip) {
+                ip if ip.is_loopback() => Scope::Device,
+                IpAddr::V4(ip) if ip.is_private() || ip.is_link_local() => Scope::Local,
+                IpAddr::V6(ip) if ip.segments()[0] & 0xffc0 == 0xfe80 => Scope::Local,
+                IpAddr::V6(ip) if ip.segments()[0] & 0xfe00 == 0xfc00 => Scope::Local,
+                _ => Scope::Public,
+            },
+            AddressType::Hostname(host) => {
+                let host = host.trim_end_matches('.').to_lowercase();
+                if host == "localhost" || host.ends_with(".localhost") {
+                    Scope::Device
+                } else if host.ends_with(".local") || !host.contains('.') {
+                    Scope::Local
+                } else {
+                    Scope::Public
+                }
+            }
+        }
+    }
+
+    /// Whether the host is `0.0.0.0` or `::`, as announced by listeners bound
+    /// to every interface. Such addresses cannot be dialed as they are.
+    pub fn is_wildcard_host(&self) -> bool {
+        match self.address {
+            AddressType::Ip(ip) => ip.is_unspecified(),
+            _ => false,
+        }
+    }
+
+    /// A copy of this address with `ip` as its host. Fails for transports
+    /// without a `host:port` pair and for `onion`, which needs a v3 name.
+    pub fn with_host(&self, ip: IpAddr) -> Result<MultiserverAddress> {
+        self.ensure_host_port("host")?;
+        let address = AddressType::Ip(ip);
+        check_onion(&self.transport, &address)?;
+        Ok(MultiserverAddress {
+            address,
+            ..self.clone()
+        })
+    }
+
+    fn ensure_host_port(&self, field: &'static str) -> Result<()> {
+        ensure!(
+            self.transport.has_host_port(),
+            FieldNotAllowed {
+                transport: self.transport.clone(),
+                field
+            }
+        );
+        Ok(())
+    }
+
+    /// A copy of this address with `host` as its host, parsed the way
+    /// `from_str` parses it: IP literals become `AddressType::Ip` and
+    /// hostnames are normalized. Fails like `with_host` does.
+    pub fn with_hostname(&self, host: &str) -> Result<MultiserverAddress> {
+        self.ensure_host_port("host")?;
+        let address = address_from_host(host)?;
+        check_onion(&self.transport, &address)?;
+        Ok(MultiserverAddress {
+            address,
+            ..self.clone()
+        })
+    }
+
+    /// A copy of this address with `port` as its port, e.g. the external
+    /// port of a forwarded pub. Fails for transports without a `host:port`
+    /// pair, whose written form has no port to change.
+    pub fn with_port(&self, port: u16) -> Result<MultiserverAddress> {
+        self.ensure_host_port("port")?;
+        Ok(MultiserverAddress {
+            port,
+            ..self.clone()
+        })
+    }
+
+    /// A copy of this address with `pub_key` as its `shs` key. A `~noauth`
+    /// layer is kept; see `as_noauth`.
+    pub fn with_pub_key(&self, pub_key: Option<Multikey>) -> MultiserverAddress {
+        MultiserverAddress {
+            security: SecurityLayer::layers(pub_key, self.is_noauth()),
+            ..self.clone()
+        }
+    }
+
+    /// One dialable copy of a wildcard address per interface address in
+    /// `local_ips`. `0.0.0.0` only expands to IPv4 addresses, while `::`
+    /// listeners accept both families. Other addresses are returned as is.
+    pub fn expand_wildcard(&self, local_ips: &[IpAddr]) -> Vec<MultiserverAddress> {
+        match self.address {
+            AddressType::Ip(ip) if ip.is_unspecified() => local_ips
+                .iter()
+                .filter(|local| ip.is_ipv6() || local.is_ipv4())
+                .filter_map(|local| self.with_host(*local).ok())
+                .collect(),
+            _ => vec![self.clone()],
+        }
+    }
+
+    /// Whether the host is an I2P destination (`*.b32.i2p` or any `.i2p` name).
+    pub fn is_i2p(&self) -> bool {
+        match &self.address {
+            AddressType::Hostname(host) => {
+                host.trim_end_matches('.').to_lowercase().ends_with(".i2p")
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the host is in cjdns (`fc00::/8`) or Yggdrasil (`200::/7`)
+    /// address space.
+    pub fn is_overlay_mesh(&self) -> bool {
+        match self.address {
+            AddressType::Ip(IpAddr::V6(ip)) => {
+                let first = ip.segments()[0];
+                first >> 8 == 0xfc || first >> 9 == 0x0200 >> 9
+            }
+            _ => false,
+        }
+    }
+
+    /// The socket address of an IP-based address; hostnames, sockets and
+    /// WebRTC signals have none without a lookup.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self.address {
+            AddressType::Ip(ip) => Some(SocketAddr::new(ip, self.port)),
+            _ => None,
+        }
+    }
+
+    /// Whether an incoming connection from `addr` could be this address.
+    /// IPv4-mapped IPv6 peers compare equal to their IPv4 form. Hostnames are
+    /// resolved through the system resolver and cached for `RESOLVE_TTL`, up
+    /// to `RESOLVE_CACHE_CAPACITY` hostnames.
+    pub fn matches_socket_addr(&self, addr: &SocketAddr) -> bool {
+        if addr.port() != self.port {
+            return false;
+        }
+        let peer = unmap_ip(addr.ip());
+        match &self.address {
+            AddressType::Ip(ip) => unmap_ip(*ip) == peer,
+            AddressType::Hostname(host) => resolve_cached(host)
+                .into_iter()
+                .any(|ip| unmap_ip(ip) == peer),
+            _ => false,
+        }
+    }
+
+    #[cfg(feature = "url")]
+    pub fn to_tcp_url(&self) -> Option<Url> {
+        self.to_url("tcp")
+    }
+
+    /// The `http://` URL of the host, port and path, e.g. the page a room
+    /// serves for an alias.
+    #[cfg(feature = "url")]
+    pub fn to_http_url(&self) -> Option<Url> {
+        self.to_url("http")
+    }
+
+    #[cfg(feature = "url")]
+    pub fn to_https_url(&self) -> Option<Url> {
+        self.to_url("https")
+    }
+
+    #[cfg(feature = "url")]
+    pub fn to_ws_url(&self) -> Option<Url> {
+        self.to_url("ws")
+    }
+
+    #[cfg(feature = "url")]
+    pub fn to_wss_url(&self) -> Option<Url> {
+        self.to_url("wss")
+    }
+
+    // Only IP and hostname addresses have a host for a URL.
+    #[cfg(feature = "url")]
+    fn to_url(&self, scheme: &str) -> Option<Url> {
+        let host = match &self.address {
+            AddressType::Ip(IpAddr::V4(ip)) => ip.to_string(),
+            AddressType::Ip(IpAddr::V6(ip)) => format!("[{}]", ip),
+            AddressType::Hostname(host) => host.clone(),
+            _ => return None,
+        };
+        let path = self.path.as_deref().unwrap_or("/");
+        Url::parse(&format!("{}://{}:{}{}", scheme, host, self.port, path)).ok()
+    }
+
+    pub fn write_to_io<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        let mut adapter = IoWriter {
+            inner: w,
+            error: None,
+        };
+        self.write_to(&mut adapter).map_err(|_| {
+            adapter
+                .error
+                .take()
+                .unwrap_or_else(|| io::ErrorKind::Other.into())
+        })
+    }
+}
+
+// Lets `write_to` drive an `io::Write`, keeping the underlying I/O error.
+struct IoWriter<'a, W: io::Write + ?Sized> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write + ?Sized> fmt::Write for IoWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+/// Resolves hostnames through the system resolver, so an address can be
+/// passed straight to `TcpStream::connect`.
+impl ToSocketAddrs for MultiserverAddress {
+    type Iter = std::vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        match &self.address {
+            AddressType::Ip(ip) => Ok(vec![SocketAddr::new(*ip, self.port)].into_iter()),
+            AddressType::Hostname(host) => (host.as_str(), self.port).to_socket_addrs(),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "address has no network host",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MultiserverAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_to(f)
+    }
+}
+
+/// Addresses order by transport, then host, then port, then key, with
+/// keyless addresses first. Path and `noauth` only break remaining ties.
+impl Ord for MultiserverAddress {
+    fn cmp(&self, other: &MultiserverAddress) -> Ordering {
+        let key = |address: &MultiserverAddress| address.pub_key().map(Multikey::to_legacy_vec);
+        self.transport
+            .cmp(&other.transport)
+            .then_with(|| self.address.cmp(&other.address))
+            .then(self.port.cmp(&other.port))
+            .then_with(|| key(self).cmp(&key(other)))
+            .then_with(|| self.path.cmp(&other.path))
+            .then(self.is_noauth().cmp(&other.is_noauth()))
+    }
+}
+
+impl PartialOrd for MultiserverAddress {
+    fn partial_cmp(&self, other: &MultiserverAddress) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Comparing against a string compares canonical forms, as `semantic_eq`
+// does, since strings may spell the same address in several ways.
+impl PartialEq<str> for MultiserverAddress {
+    fn eq(&self, other: &str) -> bool {
+        MultiserverAddress::from_str(other).is_ok_and(|other| self.semantic_eq(&other))
+    }
+}
+
+impl PartialEq<&str> for MultiserverAddress {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl TryFrom<&str> for MultiserverAddress {
+    type Error = Error;
+
+    fn try_from(st: &str) -> Result<MultiserverAddress> {
+        MultiserverAddress::from_str(st)
+    }
+}
+
+impl TryFrom<String> for MultiserverAddress {
+    type Error = Error;
+
+    fn try_from(st: String) -> Result<MultiserverAddress> {
+        MultiserverAddress::from_str(&st)
+    }
+}
+
+/// `net:<ip>:<port>~shs:<pub_key>`, e.g. for announcing a listener.
+impl From<(SocketAddr, Multikey)> for MultiserverAddress {
+    fn from((addr, pub_key): (SocketAddr, Multikey)) -> MultiserverAddress {
+        MultiserverAddress::from((addr.ip(), addr.port(), pub_key))
+    }
+}
+
+/// `net:<ip>:<port>~shs:<pub_key>`.
+impl From<(IpAddr, u16, Multikey)> for MultiserverAddress {
+    fn from((ip, port, pub_key): (IpAddr, u16, Multikey)) -> MultiserverAddress {
+        MultiserverAddress {
+            transport: Transport::Net,
+            address: AddressType::Ip(ip),
+            port,
+            path: None,
+            security: vec![SecurityLayer::Shs(pub_key)],
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-std",
+    all(feature = "web", target_arch = "wasm32")
+))]
+impl MultiserverAddress {
+    // The key the peer must prove in the secret handshake, if any.
+    fn handshake_key(&self) -> Option<Multikey> {
+        if self.is_noauth() {
+            None
+        } else {
+            self.pub_key().cloned()
+        }
+    }
+
+    // `ws://host:port/`, with IPv6 hosts bracketed.
+    fn websocket_url(&self) -> Option<String> {
+        let host = match &self.address {
+            AddressType::Ip(IpAddr::V6(ip)) => format!("[{}]", ip),
+            AddressType::Ip(ip) => ip.to_string(),
+            AddressType::Hostname(host) => host.clone(),
+            _ => return None,
+        };
+        let path = self.path.as_deref().unwrap_or("/");
+        Some(format!(
+            "{}://{}:{}{}",
+            self.transport.as_str(),
+            host,
+            self.port,
+            path
+        ))
+    }
+}
+
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-std",
+    all(feature = "web", target_arch = "wasm32")
+))]
+fn unsupported(address: &MultiserverAddress) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("cannot connect to {}", address),
+    )
+}
+
+fn check_onion(transport: &Transport, address: &AddressType) -> Result<()> {
+    if *transport == Transport::Onion {
+        let host = match address {
+            AddressType::Hostname(host) => host,
+            _ => "",
+        };
+        ensure!(
+            is_onion_v3(host),
+            OnionInvalid {
+                host: host.to_string()
+            }
+        );
+    }
+    Ok(())
+}
+
+// Addresses (and lists of them) serialize as their multiserver string form
+// in human-readable formats, since that is how they appear in configs and
+// messages, and also deserialize from a table of their parts. Binary formats
+// get the fields as a struct instead, which is smaller and needs no string
+// formatting.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use crate::list::MultiserverAddressList;
+    use crate::{AddressType, MultiserverAddress, SecurityLayer, Transport};
+    use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+    use serde::de::{self, Error as _, MapAccess, SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::fmt;
+    use std::str::FromStr;
+
+    // The compact form is tagged with its schema version, so data cached by
+    // an older release still loads. A change to the fields adds a variant
+    // here and a migration from the older ones in `Versioned::migrate`;
+    // versions this release does not know are an error.
+    #[derive(Serialize)]
+    enum VersionedRef<'a> {
+        V1(CompactRef<'a>),
+    }
+
+    #[derive(Deserialize)]
+    enum Versioned {
+        V1(Compact),
+    }
+
+    impl Versioned {
+        fn migrate(self) -> Compact {
+            match self {
+                Versioned::V1(compact) => compact,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    struct CompactRef<'a> {
+        transport: &'a Transport,
+        port: u16,
+        address: &'a AddressType,
+        path: &'a Option<String>,
+        security: &'a [SecurityLayer],
+    }
+
+    #[derive(Deserialize)]
+    struct Compact {
+        transport: Transport,
+        port: u16,
+        address: AddressType,
+        path: Option<String>,
+        security: Vec<SecurityLayer>,
+    }
+
+    impl Serialize for MultiserverAddress {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                return serializer.collect_str(self);
+            }
+            VersionedRef::V1(CompactRef {
+                transport: &self.transport,
+                port: self.port,
+                address: &self.address,
+                path: &self.path,
+                security: &self.security,
+            })
+            .serialize(serializer)
+        }
+    }
+
+    // Config files may spell an address as a table instead of a string:
+    //
+    //     [[peers]]
+    //     host = "ssb.example.com"
+    //     port = 8008
+    //     key = "HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+    //
+    // The transport defaults to `net`, and only transports with a host and
+    // port can be written this way.
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Table {
+        transport: Option<String>,
+        host: String,
+        port: Option<u16>,
+        path: Option<String>,
+        key: Option<String>,
+        #[serde(default)]
+        noauth: bool,
+    }
+
+    impl Table {
+        fn build(self) -> crate::Result<MultiserverAddress> {
+            let transport = match self.transport.as_deref().unwrap_or("net") {
+                "net" => Transport::Net,
+                "udp" => Transport::Udp,
+                "http" => Transport::Http,
+                "https" => Transport::Https,
+                "ws" => Transport::Ws,
+                "wss" => Transport::Wss,
+                "onion" => Transport::Onion,
+                name => return crate::ProtocolUnknown { name }.fail(),
+            };
+            let mut builder = MultiserverAddress::builder()
+                .transport(transport)
+                .host(&self.host);
+            if let Some(port) = self.port {
+                builder = builder.port(port);
+            }
+            if let Some(path) = &self.path {
+                builder = builder.path(path);
+            }
+            if let Some(key) = &self.key {
+                builder = builder.shs_key(crate::shs_key(key)?);
+            }
+            if self.noauth {
+                builder = builder.noauth();
+            }
+            builder.build()
+        }
+    }
+
+    struct AddressVisitor;
+
+    impl<'de> Visitor<'de> for AddressVisitor {
+        type Value = MultiserverAddress;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a multiserver address string or table")
+        }
+
+        fn visit_str<E: de::Error>(self, st: &str) -> Result<MultiserverAddress, E> {
+            MultiserverAddress::from_str(st).map_err(E::custom)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<MultiserverAddress, A::Error> {
+            let table = Table::deserialize(MapAccessDeserializer::new(map))?;
+            table.build().map_err(A::Error::custom)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for MultiserverAddress {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                return deserializer.deserialize_any(AddressVisitor);
+            }
+            let compact = Versioned::deserialize(deserializer)?.migrate();
+            let address = MultiserverAddress {
+                transport: compact.transport,
+                port: compact.port,
+                address: compact.address,
+                path: compact.path,
+                security: compact.security,
+            };
+            // Cached data is as untrusted as any other input, so the fields
+            // go through the parser's checks too.
+            MultiserverAddress::from_str(&address.to_string()).map_err(D::Error::custom)
+        }
+    }
+
+    impl Serialize for MultiserverAddressList {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                return serializer.collect_str(self);
+            }
+            self.0.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for MultiserverAddressList {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                return deserializer.deserialize_any(ListVisitor);
+            }
+            Vec::deserialize(deserializer).map(MultiserverAddressList)
+        }
+    }
+
+    // A list is either the `;`-separated string or a sequence of addresses,
+    // each a string or table.
+    struct ListVisitor;
+
+    impl<'de> Visitor<'de> for ListVisitor {
+        type Value = MultiserverAddressList;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a multiserver address list string or sequence")
+        }
+
+        fn visit_str<E: de::Error>(self, st: &str) -> Result<MultiserverAddressList, E> {
+            MultiserverAddressList::from_str(st).map_err(E::custom)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<MultiserverAddressList, A::Error> {
+            Vec::deserialize(SeqAccessDeserializer::new(seq)).map(MultiserverAddressList)
+        }
+    }
+}
+
+// Tunnel keys are stored in their `@<base64>.ed25519` form.
+#[cfg(feature = "serde")]
+mod legacy_key {
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use ssb_multiformats::multikey::Multikey;
+
+    pub fn serialize<S: Serializer>(pub_key: &Multikey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&pub_key.to_legacy_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Multikey, D::Error> {
+        let st = String::deserialize(deserializer)?;
+        crate::legacy_key(&st).map_err(D::Error::custom)
+    }
+}
+
+// An `@<base64>.ed25519` key as used by tunnel and legacy addresses.
+fn legacy_key(st: &str) -> Result<Multikey> {
+    let (pub_key, rest) =
+        Multikey::from_legacy(st.as_bytes()).map_err(|_| Error::LegacyPubKeyInvalid {})?;
+    ensure!(rest.is_empty(), LegacyPubKeyInvalid);
+    Ok(pub_key)
+}
+
+// The escape character and the separators that would otherwise end a layer
+// or an address. Protocols whose data has no `:` structure of its own, like
+// `unix`, escape `:` as well.
+const SEPARATORS: &[char] = &['!', '~', ';'];
+
+// Multiserver escapes reserved characters in protocol data by prefixing
+// them with `!`.
+fn write_escaped<W: fmt::Write + ?Sized>(w: &mut W, st: &str, reserved: &[char]) -> fmt::Result {
+    for c in st.chars() {
+        if reserved.contains(&c) {
+            w.write_char('!')?;
+        }
+        w.write_char(c)?;
+    }
+    Ok(())
+}
+
+// Splits `st` at each `separator` that is not escaped with `!`, keeping the
+// byte offset of every piece. Pieces borrow from `st`, so splitting does not
+// allocate.
+fn split_unescaped(st: &str, separator: u8) -> SplitUnescaped<'_> {
+    SplitUnescaped {
+        st,
+        separator,
+        start: Some(0),
+    }
+}
+
+struct SplitUnescaped<'a> {
+    st: &'a str,
+    separator: u8,
+    // Where the next piece starts, or `None` once the last one was returned.
+    start: Option<usize>,
+}
+
+impl<'a> Iterator for SplitUnescaped<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<(usize, &'a str)> {
+        let start = self.start?;
+        let mut escaped = false;
+        for (i, &b) in self.st.as_bytes()[start..].iter().enumerate() {
+            match b {
+                _ if escaped => escaped = false,
+                b'!' => escaped = true,
+                _ if b == self.separator => {
+                    let end = start + i;
+                    self.start = Some(end + 1);
+                    return Some((start, &self.st[start..end]));
+                }
+                _ => (),
+            }
+        }
+        self.start = None;
+        Some((start, &self.st[start..]))
+    }
+}
+
+// An IP literal if `host` is one, otherwise a hostname, IDNA-normalized to
+// lowercase punycode the way a URL host would be.
+fn address_from_host(host: &str) -> Result<AddressType> {
+    let unbracketed = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if let Ok(ip) = IpAddr::from_str(unbracketed) {
+        return Ok(AddressType::Ip(ip));
+    }
+    hostname(host)
+}
+
+#[cfg(feature = "url")]
+fn hostname(host: &str) -> Result<AddressType> {
+    match Host::parse(host).context(UrlInvalid)? {
+        Host::Domain(domain) => Ok(AddressType::Hostname(domain)),
+        Host::Ipv4(ip) => Ok(AddressType::Ip(IpAddr::V4(ip))),
+        Host::Ipv6(ip) => Ok(AddressType::Ip(IpAddr::V6(ip))),
+    }
+}
+
+// Without IDNA only plain ASCII names can be normalized, by lowercasing.
+#[cfg(not(feature = "url"))]
+fn hostname(host: &str) -> Result<AddressType> {
+    ensure!(grammar::is_ascii_hostname(host), HostnameInvalid { host });
+    Ok(AddressType::Hostname(host.to_ascii_lowercase()))
+}
+
+#[cfg(not(feature = "simd-base64"))]
+fn decode_pub_key(pub_key_str: &str) -> Result<Vec<u8>, DecodeError> {
+    decode(pub_key_str)
+}
+
+// The SIMD decoder has its own error type, so failures are re-run through
+// `base64` to keep errors identical with and without the feature.
+#[cfg(feature = "simd-base64")]
+fn decode_pub_key(pub_key_str: &str) -> Result<Vec<u8>, DecodeError> {
+    base64_simd::STANDARD
+        .decode_to_vec(pub_key_str)
+        .or_else(|_| decode(pub_key_str))
+}
+
+// The bare base64 key as it appears after `shs:`.
+fn pub_key_base64(pub_key: &Multikey) -> String {
+    let legacy = pub_key.to_legacy_string();
+    let legacy = legacy.trim_start_matches('@');
+    match legacy.rfind('.') {
+        Some(i) => legacy[..i].to_string(),
+        None => legacy.to_string(),
+    }
+}
+
+// The key of an `shs` layer. Algorithms other than ed25519 are decoded by
+// `Multikey`, and a sigil `@` is never written back.
+fn shs_key(st: &str) -> Result<Multikey> {
+    let (data, algorithm) = grammar::split_key(st);
+    let bytes = decode_pub_key(data).context(PubKeyNotBase64)?;
+    if algorithm == "ed25519" {
+        return Ok(Multikey::from_ed25519(&array_32_from_vec(bytes)?));
+    }
+    match Multikey::from_legacy(format!("@{}.{}", data, algorithm).as_bytes()) {
+        Ok((key, [])) => Ok(key),
+        _ => KeyAlgorithmUnknown { algorithm }.fail(),
+    }
+}
+
+// The inverse of `shs_key`.
+fn shs_key_string(pub_key: &Multikey) -> String {
+    let legacy = pub_key.to_legacy_string();
+    let legacy = legacy.trim_start_matches('@');
+    legacy.trim_end_matches(".ed25519").to_string()
+}
+
+// ed25519 keys are exactly 32 bytes; anything else is rejected rather than
+// padded or truncated.
+fn array_32_from_vec(vec: Vec<u8>) -> Result<[u8; 32]> {
+    let mut pub_key_bytes = [0; 32];
+    ensure!(
+        vec.len() == pub_key_bytes.len(),
+        PubKeyWrongLength { actual: vec.len() }
+    );
+    pub_key_bytes.copy_from_slice(&vec);
+    Ok(pub_key_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn multiserver_ipv4_parse_ok() {
+        let valid_ms_address =
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.port, 8008);
+        assert_eq!(
+            address.pub_key().unwrap().to_legacy_string(),
+            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        );
+        match address.address {
+            AddressType::Ip(add) => assert!(add.is_ipv4()),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn multiserver_ipv6_1_parse_ok() {
+        let valid_ms_address = "net:1200:0000:AB00:1234:0000:2552:7777:1313:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.port, 8008);
+        assert_eq!(
+            address.pub_key().unwrap().to_legacy_string(),
+            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        );
+        match address.address {
+            AddressType::Ip(add) => assert!(add.is_ipv6()),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn multiserver_ipv6_2_parse_ok() {
+        let valid_ms_address = "net:21DA:D3:0:2F3B:2AA:FF:FE28:9C5A:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.port, 8008);
+        assert_eq!(
+            address.pub_key().unwrap().to_legacy_string(),
+            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        );
+        match address.address {
+            AddressType::Ip(add) => assert!(add.is_ipv6()),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn multiserver_ipv6_3_parse_ok() {
+        let valid_ms_address = "net:FE80:0000:0000:0000:0202:B3FF:FE1E:8329:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.port, 8008);
+        assert_eq!(
+            address.pub_key().unwrap().to_legacy_string(),
+            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        );
+        match address.address {
+            AddressType::Ip(add) => assert!(add.is_ipv6()),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn multiserver_url_parse_ok() {
+        let valid_ms_address = "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.port, 8008);
+        assert_eq!(
+            address.pub_key().unwrap().to_legacy_string(),
+            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        );
+        match address.address {
+            AddressType::Hostname(_) => (),
+            _ => panic!(),
+        };
+    }
+    #[test]
+    fn multiserver_too_long_err() {
+        let padding = "a".repeat(MAX_ADDRESS_LEN);
+        let invalid_ms_address = format!(
+            "net:{}.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            padding
+        );
+        match MultiserverAddress::from_str(&invalid_ms_address) {
+            Err(Error::InputTooLong { length }) => assert!(length > MAX_ADDRESS_LEN),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn multiserver_too_many_segments_err() {
+        let invalid_ms_address = format!(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4={}",
+            "~".repeat(MAX_SEGMENTS)
+        );
+        match MultiserverAddress::from_str(&invalid_ms_address) {
+            Err(Error::TooManySegments { .. }) => (),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn multiserver_stable_hash_ok() {
+        let a = MultiserverAddress::from_str(
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let b = MultiserverAddress::from_str(
+            "net:192.168.178.17:8009~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(a.stable_hash(), a.clone().stable_hash());
+        assert_ne!(a.stable_hash(), b.stable_hash());
+        assert!(a.cache_key().starts_with("v3:"));
+        assert_eq!(a.cache_key().len(), 3 + 64);
+
+        let spellings = ["net:Host.COM.:8008~noauth", "net:host.com:8008~noauth"];
+        let [c, d] = spellings.map(|st| MultiserverAddress::from_str(st).unwrap());
+        assert_eq!(c.stable_hash(), d.stable_hash());
+        let mapped = MultiserverAddress::from_str("net:[::ffff:192.168.178.17]:8008~noauth");
+        let plain = MultiserverAddress::from_str("net:192.168.178.17:8008~noauth");
+        assert_eq!(mapped.unwrap().stable_hash(), plain.unwrap().stable_hash());
+    }
+    #[test]
+    fn multiserver_eq_str_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:1200:0000:AB00:1234:0000:2552:7777:1313:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(
+            address,
+            "net:1200:0:ab00:1234:0:2552:7777:1313:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        );
+        assert_ne!(address, "not an address");
+
+        let host = MultiserverAddress::from_str("net:host.com:8008~noauth").unwrap();
+        assert_eq!(host, *"net:Host.COM.:8008~noauth");
+        assert_ne!(host, *"net:host.com:8009~noauth");
+    }
+    #[test]
+    fn multiserver_error_kind_ok() {
+        let err = MultiserverAddress::from_str("not an address").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Syntax);
+
+        let err = MultiserverAddress::from_str(
+            "net:192.168.178.17:99999~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap_err();
+        assert!(err.is_semantic());
+
+        let err = MultiserverAddress::from_str(&"~".repeat(MAX_SEGMENTS)).unwrap_err();
+        assert!(err.is_limit());
+    }
+    #[test]
+    fn multiserver_write_to_ok() {
+        for valid_ms_address in &[
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            "net:[fe80::202:b3ff:fe1e:8329]:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        ] {
+            let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+
+            let mut written = String::new();
+            address.write_to(&mut written).unwrap();
+            assert_eq!(&written, valid_ms_address);
+
+            let mut buffer = Vec::new();
+            address.write_to_io(&mut buffer).unwrap();
+            assert_eq!(buffer, valid_ms_address.as_bytes());
+        }
+    }
+    #[cfg(feature = "url")]
+    #[test]
+    fn multiserver_to_url_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:host.com:8989~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(address.to_ws_url().unwrap().as_str(), "ws://host.com:8989/");
+        assert_eq!(
+            address.to_wss_url().unwrap().as_str(),
+            "wss://host.com:8989/"
+        );
+        assert_eq!(
+            address.to_tcp_url().unwrap().as_str(),
+            "tcp://host.com:8989/"
+        );
+
+        let address = MultiserverAddress::from_str(
+            "net:FE80:0000:0000:0000:0202:B3FF:FE1E:8329:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(
+            address.to_ws_url().unwrap().as_str(),
+            "ws://[fe80::202:b3ff:fe1e:8329]:8008/"
+        );
+    }
+    #[test]
+    fn multiserver_http_parse_ok() {
+        let valid_ms_address =
+            "https:room.example.com/alias~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.transport, Transport::Https);
+        assert_eq!(address.port, 443);
+        assert_eq!(address.path.as_deref(), Some("/alias"));
+        match &address.address {
+            AddressType::Hostname(host) => assert_eq!(host, "room.example.com"),
+            _ => panic!(),
+        }
+
+        let mut written = String::new();
+        address.write_to(&mut written).unwrap();
+        assert_eq!(written, valid_ms_address);
+    }
+    #[test]
+    fn multiserver_ws_path_ok() {
+        let valid_ms_address =
+            "wss:hub.example.com/ssb-ws~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.transport, Transport::Wss);
+        assert_eq!(address.path.as_deref(), Some("/ssb-ws"));
+        assert_eq!(address.to_string(), valid_ms_address);
+    }
+    #[test]
+    fn multiserver_default_port_ok() {
+        let key = "~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(&format!("wss:hub.example.com{}", key)).unwrap();
+        assert_eq!(address.port, 443);
+        assert_eq!(address.to_string(), format!("wss:hub.example.com{}", key));
+        assert_eq!(
+            MultiserverAddress::from_str(&format!("wss:hub.example.com:443{}", key)).unwrap(),
+            address
+        );
+
+        let ws = MultiserverAddress::from_str("ws:[::1]/ssb~noauth").unwrap();
+        assert_eq!((ws.port, ws.path.as_deref()), (80, Some("/ssb")));
+        assert_eq!(ws.to_string(), "ws:[::1]/ssb~noauth");
+        let custom = MultiserverAddress::from_str("http:1.2.3.4:8080~noauth").unwrap();
+        assert_eq!(custom.to_string(), "http:1.2.3.4:8080~noauth");
+
+        assert!(MultiserverAddress::from_str("net:host.com~noauth").is_err());
+        assert_eq!(
+            crate::stack::ProtocolStack::from_str(&address.to_string())
+                .unwrap()
+                .to_string(),
+            address.to_string()
+        );
+    }
+    #[cfg(feature = "url")]
+    #[test]
+    fn multiserver_http_url_ok() {
+        let address = MultiserverAddress::from_str("https:room.example.com:443/alias").unwrap();
+        assert_eq!(
+            address.to_https_url().unwrap().as_str(),
+            "https://room.example.com/alias"
+        );
+        assert_eq!(
+            address.to_http_url().unwrap().as_str(),
+            "http://room.example.com:443/alias"
+        );
+    }
+    #[test]
+    fn multiserver_net_path_err() {
+        let invalid_ms_address =
+            "net:host.com:8008/path~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        assert!(MultiserverAddress::from_str(invalid_ms_address).is_err());
+    }
+    #[test]
+    fn multiserver_url_path_err() {
+        for input in [
+            "wss:host.com/a b~noauth",
+            "ws:host.com:80/a?b",
+            "https:host.com/%zz",
+        ] {
+            assert!(matches!(
+                MultiserverAddress::from_str(input),
+                Err(Error::PathInvalid {})
+            ));
+            assert!(!MultiserverAddress::is_valid(input));
+            assert!(crate::stack::ProtocolStack::from_str(input).is_err());
+        }
+        let escaped = MultiserverAddress::from_str("wss:host.com/a%20b~noauth").unwrap();
+        assert_eq!(escaped.to_string(), "wss:host.com/a%20b~noauth");
+        assert!(MultiserverAddress::builder()
+            .transport(Transport::Wss)
+            .host("host.com")
+            .path("/a b")
+            .build()
+            .is_err());
+    }
+    #[cfg(feature = "quic")]
+    #[test]
+    fn multiserver_quic_parse_ok() {
+        let valid_ms_address =
+            "quic:host.com:443:sha256-AbCd~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(
+            address.transport,
+            Transport::Quic {
+                cert_hash: Some("sha256-AbCd".to_string())
+            }
+        );
+        assert_eq!(address.port, 443);
+
+        let mut written = String::new();
+        address.write_to(&mut written).unwrap();
+        assert_eq!(written, valid_ms_address);
+    }
+    #[test]
+    fn multiserver_cert_hash_err() {
+        let invalid_ms_address =
+            "net:host.com:8008:sha256-AbCd~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        assert!(MultiserverAddress::from_str(invalid_ms_address).is_err());
+    }
+    #[test]
+    fn multiserver_udp_parse_ok() {
+        let valid_ms_address =
+            "udp:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.transport, Transport::Udp);
+        assert_eq!(address.port, 8008);
+        match address.address {
+            AddressType::Ip(add) => assert!(add.is_ipv4()),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn multiserver_webrtc_parse_ok() {
+        let valid_ms_address =
+            "webrtc:signal.example.com:room=abc~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(valid_ms_address).unwrap();
+        assert_eq!(address.transport, Transport::WebRtc);
+        assert_eq!(
+            address.address,
+            AddressType::WebRtc("signal.example.com:room=abc".to_string())
+        );
+
+        let mut written = String::new();
+        address.write_to(&mut written).unwrap();
+        assert_eq!(written, valid_ms_address);
+    }
+    #[test]
+    fn multiserver_i2p_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:ukeu3k5oycgaauneqgtnvselmt4yemvoilkln7jpvamvfx7dnkdq.b32.i2p:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert!(address.is_i2p());
+
+        let address = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert!(!address.is_i2p());
+    }
+    #[test]
+    fn multiserver_overlay_mesh_ok() {
+        for (valid_ms_address, is_overlay_mesh) in &[
+            ("net:fc12:3456:789a:bcde:f012:3456:789a:bcde:8008", true),
+            ("net:201:a:b:c:d:e:f:1:8008", true),
+            ("net:400:a:b:c:d:e:f:1:8008", false),
+            ("net:fe80:0:0:0:202:b3ff:fe1e:8329:8008", false),
+            ("net:192.168.178.17:8008", false),
+        ] {
+            let address = MultiserverAddress::from_str(&format!(
+                "{}~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+                valid_ms_address
+            ))
+            .unwrap();
+            assert_eq!(address.is_overlay_mesh(), *is_overlay_mesh);
+        }
+    }
+    #[test]
+    fn multiserver_loopback_ok() {
+        let pub_key = Multikey::from_ed25519(&[1; 32]);
+        let address = MultiserverAddress::loopback(8008, pub_key.clone());
+        assert_eq!(address.port, 8008);
+        assert_eq!(address.pub_key(), Some(&pub_key));
+        match address.address {
+            AddressType::Ip(ip) => assert!(ip.is_loopback()),
+            _ => panic!(),
+        }
+
+        let mut written = String::new();
+        MultiserverAddress::localhost(8009)
+            .write_to(&mut written)
+            .unwrap();
+        assert_eq!(written, "net:localhost:8009~noauth");
+    }
+    #[test]
+    fn multiserver_control_character_err() {
+        let address = "net:host.com\0:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        match MultiserverAddress::from_str(address) {
+            Err(Error::ControlCharacter { offset }) => assert_eq!(offset, 12),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+    #[test]
+    fn multiserver_ascii_only_ok() {
+        let address = "net:bücher.de:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        // Unicode hostnames need IDNA from the `url` feature.
+        assert_eq!(
+            MultiserverAddress::from_str(address).is_ok(),
+            cfg!(feature = "url")
+        );
+        match MultiserverAddress::from_ascii_str(address) {
+            Err(Error::NonAscii { offset }) => assert_eq!(offset, 5),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(MultiserverAddress::from_ascii_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )
+        .is_ok());
+    }
+    #[test]
+    fn multiserver_auth_toggle_ok() {
+        let address = MultiserverAddress::from_str(
+            "https:room.example.com:443/alias~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let noauth = address.as_noauth();
+        assert_eq!(noauth.pub_key(), None);
+        assert_eq!(noauth.path, address.path);
+        assert_eq!(
+            noauth.authenticated_with(address.pub_key().cloned().unwrap()),
+            address
+        );
+    }
+    #[test]
+    fn multiserver_matches_socket_addr_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert!(address.matches_socket_addr(&"192.168.178.17:8008".parse().unwrap()));
+        assert!(address.matches_socket_addr(&"[::ffff:192.168.178.17]:8008".parse().unwrap()));
+        assert!(!address.matches_socket_addr(&"192.168.178.17:8009".parse().unwrap()));
+        assert!(!address.matches_socket_addr(&"192.168.178.18:8008".parse().unwrap()));
+
+        let localhost = MultiserverAddress::localhost(8008);
+        assert!(localhost.matches_socket_addr(&"127.0.0.1:8008".parse().unwrap()));
+    }
+    #[test]
+    fn multiserver_resolve_cache_bounded_ok() {
+        let now = Instant::now();
+        let entry = |at: Instant, used: Instant| Resolved {
+            at,
+            used,
+            ips: Vec::new(),
+        };
+        let mut resolved = HashMap::new();
+        resolved.insert("old.com".to_string(), entry(now, now));
+        resolved.insert(
+            "recent.com".to_string(),
+            entry(now, now + Duration::from_secs(1)),
+        );
+        make_room(&mut resolved, "new.com", now, 2);
+        assert_eq!(resolved.len(), 1);
+        assert!(resolved.contains_key("recent.com"));
+
+        let later = now + RESOLVE_TTL;
+        resolved.insert("fresh.com".to_string(), entry(later, later));
+        make_room(&mut resolved, "new.com", later, 2);
+        assert_eq!(resolved.keys().collect::<Vec<_>>(), ["fresh.com"]);
+    }
+    #[test]
+    fn multiserver_display_round_trip_ok() {
+        for address in &[
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            "https:room.example.com/alias~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            "net:[fe80::202:b3ff:fe1e:8329]:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        ] {
+            let parsed = MultiserverAddress::from_str(address).unwrap();
+            assert_eq!(parsed.to_string(), *address);
+            assert_eq!(
+                MultiserverAddress::from_str(&parsed.to_string()).unwrap(),
+                parsed
+            );
+        }
+    }
+    #[test]
+    fn multiserver_ws_parse_ok() {
+        let address = MultiserverAddress::from_str(
+            "wss:example.com:443~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(address.transport, Transport::Wss);
+        assert_eq!(address.port, 443);
+        assert_eq!(
+            address.to_string(),
+            "wss:example.com~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        );
+
+        let address = MultiserverAddress::from_str(
+            "ws:10.0.0.1:8989~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(address.transport, Transport::Ws);
+    }
+    #[test]
+    fn multiserver_unix_parse_ok() {
+        let address = MultiserverAddress::from_str(
+            "unix:/run/ssb/socket~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(address.transport, Transport::Unix);
+        assert_eq!(
+            address.address,
+            AddressType::SocketFilePath("/run/ssb/socket".to_string())
+        );
+
+        let escaped = "unix:/tmp/a!:b!!c~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(escaped).unwrap();
+        assert_eq!(
+            address.address,
+            AddressType::SocketFilePath("/tmp/a:b!c".to_string())
+        );
+        assert_eq!(address.to_string(), escaped);
+    }
+    #[test]
+    fn multiserver_onion_parse_ok() {
+        let address = MultiserverAddress::from_str("onion:vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=").unwrap();
+        assert_eq!(address.transport, Transport::Onion);
+        assert_eq!(address.port, 8008);
+
+        match MultiserverAddress::from_str(
+            "onion:3wmnbu4vxpgzfvhn.onion:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        ) {
+            Err(err @ Error::OnionInvalid { .. }) => assert!(err.is_semantic()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn multiserver_serde_ok() {
+        let input = "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(input).unwrap();
+        assert_eq!(
+            serde_json::to_value(&address).unwrap(),
+            serde_json::json!(input)
+        );
+        let decoded: MultiserverAddress = serde_json::from_value(serde_json::json!(input)).unwrap();
+        assert_eq!(decoded, address);
+        assert!(serde_json::from_value::<MultiserverAddress>(serde_json::json!("nope")).is_err());
+
+        let structured = serde_json::to_value(&address.address).unwrap();
+        assert_eq!(structured, serde_json::json!({ "Hostname": "host.com" }));
+        assert_eq!(
+            serde_json::from_value::<AddressType>(structured).unwrap(),
+            address.address
+        );
+        assert_eq!(
+            serde_json::to_value(&Transport::Wss).unwrap(),
+            serde_json::json!("wss")
+        );
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn multiserver_serde_config_ok() {
+        #[derive(serde::Deserialize)]
+        struct Config {
+            peers: Vec<MultiserverAddress>,
+            fallback: list::MultiserverAddressList,
+        }
+        let expected = [
+            MultiserverAddress::from_str(
+                "net:ssb.example.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            )
+            .unwrap(),
+            MultiserverAddress::from_str("wss:[::1]/ssb~noauth").unwrap(),
+            MultiserverAddress::from_str("net:10.0.0.1:8008~noauth").unwrap(),
+        ];
+
+        let config: Config = toml::from_str(
+            r#"
+            fallback = "net:10.0.0.1:8008~noauth"
+
+            [[peers]]
+            host = "ssb.example.com"
+            port = 8008
+            key = "HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+
+            [[peers]]
+            transport = "wss"
+            host = "::1"
+            path = "/ssb"
+            noauth = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.peers, expected[..2]);
+        assert_eq!(config.fallback[..], expected[2..]);
+
+        let config: Config = serde_yaml::from_str(
+            r#"
+            peers:
+              - "net:ssb.example.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+              - { transport: wss, host: "::1", path: /ssb, noauth: true }
+            fallback:
+              - host: 10.0.0.1
+                port: 8008
+                noauth: true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.peers, expected[..2]);
+        assert_eq!(config.fallback[..], expected[2..]);
+
+        assert!(toml::from_str::<Config>(
+            r#"
+            fallback = "net:10.0.0.1:8008~noauth"
+            peers = [{ transport = "unix", host = "/tmp/ssb.sock" }]
+            "#
+        )
+        .is_err());
+        assert!(serde_yaml::from_str::<Config>(
+            "{peers: [{host: host.com, port: 1, colour: red}], fallback: []}"
+        )
+        .is_err());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn multiserver_serde_compact_ok() {
+        let input =
+            "wss:room.example.com/ssb~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=~noauth";
+        let address = MultiserverAddress::from_str(input).unwrap();
+        let bytes = bincode::serialize(&address).unwrap();
+        assert_ne!(bytes, bincode::serialize(input).unwrap());
+        assert_eq!(
+            bincode::deserialize::<MultiserverAddress>(&bytes).unwrap(),
+            address
+        );
+
+        let list = list::MultiserverAddressList(vec![address.clone(), address.with_pub_key(None)]);
+        let bytes = bincode::serialize(&list).unwrap();
+        assert_eq!(
+            bincode::deserialize::<list::MultiserverAddressList>(&bytes).unwrap(),
+            list
+        );
+
+        let invalid = MultiserverAddress {
+            transport: Transport::Net,
+            ..address.clone()
+        };
+        let bytes = bincode::serialize(&invalid).unwrap();
+        assert!(bincode::deserialize::<MultiserverAddress>(&bytes).is_err());
+
+        // The first four bytes are the schema version's variant index.
+        let mut bytes = bincode::serialize(&address).unwrap();
+        bytes[..4].copy_from_slice(&7u32.to_le_bytes());
+        assert!(bincode::deserialize::<MultiserverAddress>(&bytes).is_err());
+    }
+    #[test]
+    fn multiserver_pub_key_length_err() {
+        for len in &[31, 64] {
+            let address = format!("net:host.com:8008~shs:{}", base64::encode(vec![0; *len]));
+            match MultiserverAddress::from_str(&address) {
+                Err(Error::PubKeyWrongLength { actual }) => assert_eq!(actual, *len),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+    #[test]
+    fn multiserver_socket_addr_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let expected: SocketAddr = "192.168.178.17:8008".parse().unwrap();
+        assert_eq!(address.socket_addr(), Some(expected));
+        assert_eq!(
+            address.to_socket_addrs().unwrap().collect::<Vec<_>>(),
+            vec![expected]
+        );
+
+        let localhost = MultiserverAddress::localhost(8008);
+        assert_eq!(localhost.socket_addr(), None);
+        assert!(localhost
+            .to_socket_addrs()
+            .unwrap()
+            .any(|addr| addr.ip().is_loopback() && addr.port() == 8008));
+    }
+    #[test]
+    fn multiserver_scope_ok() {
+        let scope = |host: &str| {
+            MultiserverAddress::from_str(&format!(
+                "net:{}:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+                host
+            ))
+            .unwrap()
+            .scope()
+        };
+        assert_eq!(scope("127.0.0.1"), Scope::Device);
+        assert_eq!(scope("localhost"), Scope::Device);
+        assert_eq!(scope("192.168.178.17"), Scope::Local);
+        assert_eq!(scope("fe80:0:0:0:202:b3ff:fe1e:8329"), Scope::Local);
+        assert_eq!(scope("printer.local"), Scope::Local);
+        assert_eq!(scope("8.8.8.8"), Scope::Public);
+        assert_eq!(scope("host.com"), Scope::Public);
+        assert_eq!(scope("host.com").as_str(), "public");
+        let socket = MultiserverAddress::from_str(
+            "unix:/run/ssb/socket~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(socket.scope(), Scope::Device);
+    }
+    #[test]
+    fn multiserver_bt_parse_ok() {
+        let input = "bt:00a1b2c3d4e5~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(input).unwrap();
+        assert_eq!(address.transport, Transport::Bt);
+        assert_eq!(
+            address.address,
+            AddressType::Bluetooth("00a1b2c3d4e5".to_string())
+        );
+        assert_eq!(address.to_string(), input);
+        assert!(MultiserverAddress::from_str(
+            "bt:00a1b2c3d4~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )
+        .is_err());
+    }
+    #[test]
+    fn multiserver_tunnel_parse_ok() {
+        let input = "tunnel:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519:@AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.ed25519~shs:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+        let address = MultiserverAddress::from_str(input).unwrap();
+        assert_eq!(address.transport, Transport::Tunnel);
+        assert_eq!(
+            address.portal_key().unwrap().to_legacy_string(),
+            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        );
+        assert_eq!(address.target_key(), address.pub_key());
+        assert_eq!(address.to_string(), input);
+
+        let built = MultiserverAddress::tunnel_via(
+            address.portal_key().cloned().unwrap(),
+            address.target_key().cloned().unwrap(),
+            address.pub_key().cloned().unwrap(),
+        );
+        assert_eq!(built, address);
+    }
+    #[test]
+    fn multiserver_dht_parse_ok() {
+        let input = "dht:8f3a9c0d1e2b~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(input).unwrap();
+        assert_eq!(address.transport, Transport::Dht);
+        assert_eq!(
+            address.address,
+            AddressType::Dht("8f3a9c0d1e2b".to_string())
+        );
+        assert_eq!(address.to_string(), input);
+        assert!(MultiserverAddress::from_str(
+            "dht:~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )
+        .is_err());
+    }
+    #[test]
+    fn multiserver_noauth_parse_ok() {
+        let address = MultiserverAddress::from_str("net:localhost:8008~noauth").unwrap();
+        assert_eq!(address.pub_key(), None);
+        assert_eq!(address.security_layers(), vec![SecurityLayer::Noauth]);
+        assert_eq!(address, MultiserverAddress::localhost(8008));
+        assert_eq!(address.to_string(), "net:localhost:8008~noauth");
+
+        let input = "ws:localhost:8989~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=~noauth";
+        let address = MultiserverAddress::from_str(input).unwrap();
+        assert!(address.is_noauth());
+        assert_eq!(address.security_layers().len(), 2);
+        assert_eq!(address.to_string(), input);
+    }
+    #[test]
+    fn multiserver_canonicalize_ok() {
+        let parse = |st: &str| {
+            MultiserverAddress::from_str(&format!(
+                "{}~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+                st
+            ))
+            .unwrap()
+        };
+        let a = parse("net:Host.COM.:8008");
+        let b = parse("net:host.com:8008");
+        assert_ne!(a, b);
+        assert!(a.semantic_eq(&b));
+        assert_eq!(a.canonicalize(), b);
+
+        let mapped = parse("net:0:0:0:0:0:ffff:c0a8:b211:8008");
+        assert!(mapped.semantic_eq(&parse("net:192.168.178.17:8008")));
+        assert!(!b.semantic_eq(&parse("net:host.com:8009")));
+    }
+    #[test]
+    fn multiserver_ipv6_digits_parse_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:0:0:0:0:0:ffff:c0a8:b211:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(address.port, 8008);
+        assert_eq!(
+            address.address,
+            AddressType::Ip(IpAddr::from_str("::ffff:192.168.178.17").unwrap())
+        );
+    }
+    #[test]
+    fn multiserver_bracketed_ipv6_parse_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:[::1]:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(
+            address.address,
+            AddressType::Ip(IpAddr::from_str("::1").unwrap())
+        );
+        assert_eq!(address.port, 8008);
+
+        let address = MultiserverAddress::from_str(
+            "ws:[fe80::1]:8989~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(address.transport, Transport::Ws);
+        assert_eq!(
+            address.to_string(),
+            "ws:[fe80::1]:8989~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        );
+    }
+    #[test]
+    fn multiserver_no_security_layer_ok() {
+        let address = MultiserverAddress::from_str("net:192.168.1.5:8008").unwrap();
+        assert_eq!(address.pub_key(), None);
+        assert!(!address.is_noauth());
+        assert_eq!(address.port, 8008);
+        assert_eq!(address.to_string(), "net:192.168.1.5:8008");
+
+        let address = MultiserverAddress::from_str("net:0:0:0:0:0:ffff:c0a8:b211:8008").unwrap();
+        assert_eq!(address.port, 8008);
+        assert!(MultiserverAddress::from_str("net:host.com:8008~foo:bar").is_err());
+    }
+    #[cfg(feature = "url")]
+    #[test]
+    fn multiserver_idn_parse_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:höflich.example:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert_eq!(address.hostname(), Some("xn--hflich-wxa.example"));
+        assert_eq!(
+            address.hostname_unicode().as_deref(),
+            Some("höflich.example")
+        );
+        assert_eq!(
+            address,
+            MultiserverAddress::from_str(
+                "net:xn--hflich-wxa.example:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            )
+            .unwrap()
+        );
+    }
+    #[cfg(feature = "url")]
+    #[test]
+    #[allow(deprecated)]
+    fn multiserver_hostname_url_compat_ok() {
+        let address = MultiserverAddress::localhost(8008);
+        assert_eq!(
+            address.address,
+            AddressType::Hostname("localhost".to_string())
+        );
+        assert_eq!(address.address.url().unwrap().as_str(), "tcp:///localhost");
+        assert_eq!(address.address.url().unwrap().path(), "/localhost");
+    }
+    #[test]
+    #[cfg(feature = "macros")]
+    fn multiserver_macro_ok() {
+        let address = crate::msaddr!(
+            "net:ssb.example.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        );
+        assert_eq!(address.port, 8008);
+        assert_eq!(
+            address.address,
+            AddressType::Hostname("ssb.example.com".to_string())
+        );
+    }
+    #[test]
+    #[cfg(feature = "macros")]
+    fn multiserver_macro_matches_parser_ok() {
+        use crate::msaddr;
+
+        let expansions = [
+            (
+                msaddr!("net:1.2.3.4:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="),
+                "net:1.2.3.4:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            ),
+            (
+                msaddr!("net:[::1]:8008~shs:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519~noauth"),
+                "net:[::1]:8008~shs:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519~noauth",
+            ),
+            (
+                msaddr!("wss:Hub.Example.com/ssb~noauth"),
+                "wss:Hub.Example.com/ssb~noauth",
+            ),
+            (msaddr!("unix:/tmp/a!:b~noauth"), "unix:/tmp/a!:b~noauth"),
+            (msaddr!("bt:0123456789ab~noauth"), "bt:0123456789ab~noauth"),
+            (
+                msaddr!("tunnel:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519:@AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.ed25519~noauth"),
+                "tunnel:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519:@AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.ed25519~noauth",
+            ),
+        ];
+        for (expanded, input) in expansions.iter() {
+            assert_eq!(*expanded, MultiserverAddress::from_str(input).unwrap());
+        }
+    }
+    #[test]
+    fn multiserver_expand_wildcard_ok() {
+        let key = "~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let local_ips = [
+            IpAddr::from_str("192.168.1.5").unwrap(),
+            IpAddr::from_str("fe80::1").unwrap(),
+        ];
+        let v4 = MultiserverAddress::from_str(&format!("net:0.0.0.0:8008{}", key)).unwrap();
+        assert!(v4.is_wildcard_host());
+        assert_eq!(
+            v4.expand_wildcard(&local_ips),
+            vec![MultiserverAddress::from_str(&format!("net:192.168.1.5:8008{}", key)).unwrap()]
+        );
+
+        let v6 = MultiserverAddress::from_str(&format!("net:[::]:8008{}", key)).unwrap();
+        assert!(v6.is_wildcard_host());
+        let expanded = v6.expand_wildcard(&local_ips);
+        assert_eq!(expanded.len(), 2);
+        assert_eq!(
+            expanded[1],
+            MultiserverAddress::from_str(&format!("net:[fe80::1]:8008{}", key)).unwrap()
+        );
+
+        let concrete = &expanded[0];
+        assert!(!concrete.is_wildcard_host());
+        assert_eq!(concrete.expand_wildcard(&local_ips), vec![concrete.clone()]);
+    }
+    #[test]
+    fn multiserver_with_modifiers_ok() {
+        let key = "~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(&format!("net:10.0.0.1:8008{}", key)).unwrap();
+        let forwarded = address
+            .with_hostname("Pub.Example.com")
+            .unwrap()
+            .with_port(443)
+            .unwrap();
+        assert_eq!(
+            forwarded.to_string(),
+            format!("net:pub.example.com:443{}", key)
+        );
+        assert_eq!(
+            address.with_hostname("[::1]").unwrap().address,
+            AddressType::Ip(IpAddr::from_str("::1").unwrap())
+        );
+        assert!(address.with_hostname("").is_err());
+        assert_eq!(
+            address
+                .with_host(IpAddr::from_str("10.0.0.2").unwrap())
+                .unwrap()
+                .to_string(),
+            format!("net:10.0.0.2:8008{}", key)
+        );
+
+        let onion = MultiserverAddress::from_str(
+            "onion:vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion:8008~noauth",
+        )
+        .unwrap();
+        let unix = MultiserverAddress::from_str("unix:/tmp/s~noauth").unwrap();
+        for address in [onion, unix].iter() {
+            assert!(address
+                .with_host(IpAddr::from_str("1.2.3.4").unwrap())
+                .is_err());
+        }
+
+        let unkeyed = address.with_pub_key(None);
+        assert_eq!(unkeyed.to_string(), "net:10.0.0.1:8008");
+        assert_eq!(unkeyed.with_pub_key(address.pub_key().cloned()), address);
+    }
+    #[test]
+    fn multiserver_ord_ok() {
+        let key = "~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let mut addresses: Vec<MultiserverAddress> = [
+            format!("ws:a.com:80{}", key),
+            format!("net:b.com:8008{}", key),
+            format!("net:a.com:8009{}", key),
+            "net:a.com:8008~noauth".to_string(),
+            format!("net:a.com:8008{}", key),
+            format!("net:1.2.3.4:8008{}", key),
+        ]
+        .iter()
+        .map(|st| MultiserverAddress::from_str(st).unwrap())
+        .collect();
+        addresses.sort();
+        let sorted: Vec<String> = addresses.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            sorted,
+            vec![
+                "net:a.com:8008~noauth".to_string(),
+                format!("net:a.com:8008{}", key),
+                format!("net:a.com:8009{}", key),
+                format!("net:b.com:8008{}", key),
+                format!("net:1.2.3.4:8008{}", key),
+                format!("ws:a.com{}", key),
+            ]
+        );
+        let set: std::collections::BTreeSet<_> = addresses.iter().cloned().collect();
+        assert_eq!(set.len(), addresses.len());
+    }
+    #[test]
+    fn multiserver_key_algorithm_ok() {
+        let bare = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let suffixed = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519",
+        )
+        .unwrap();
+        assert_eq!(bare, suffixed);
+        assert_eq!(suffixed.key_algorithm().as_deref(), Some("ed25519"));
+        assert_eq!(suffixed.to_string(), bare.to_string());
+        assert_eq!(bare.as_noauth().key_algorithm(), None);
+
+        match MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.bbfeed-v1",
+        ) {
+            Err(Error::KeyAlgorithmUnknown { algorithm }) => assert_eq!(algorithm, "bbfeed-v1"),
+            other => panic!("{:?}", other),
+        }
+    }
+    #[test]
+    fn multiserver_sigil_key_ok() {
+        let bare = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let sigil = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519",
+        )
+        .unwrap();
+        assert_eq!(sigil, bare);
+        assert_eq!(sigil.to_string(), bare.to_string());
+        match MultiserverAddress::from_str(
+            "net:host.com:8008~shs:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.bbfeed-v1",
+        ) {
+            Err(Error::KeyAlgorithmUnknown { algorithm }) => assert_eq!(algorithm, "bbfeed-v1"),
+            other => panic!("{:?}", other),
+        }
+    }
+    #[test]
+    fn multiserver_parse_without_key_ok() {
+        let st = "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let undecoded = MultiserverAddress::parse_without_key(st).unwrap();
+        assert_eq!(undecoded.address.port, 8008);
+        assert_eq!(undecoded.address.pub_key(), None);
+        assert_eq!(
+            undecoded.pub_key,
+            Some("HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=")
+        );
+        assert_eq!(
+            undecoded.decode().unwrap(),
+            MultiserverAddress::from_str(st).unwrap()
+        );
+
+        // A key of the wrong length only fails once decoded.
+        let short = "net:host.com:8008~shs:AAAAAA==";
+        let undecoded = MultiserverAddress::parse_without_key(short).unwrap();
+        assert!(matches!(
+            undecoded.decode(),
+            Err(Error::PubKeyWrongLength { .. })
+        ));
+    }
+    #[test]
+    fn multiserver_parse_strict_ok() {
+        let key = "~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let canonical = format!("net:[fe80::1]:8008{}", key);
+        assert!(MultiserverAddress::parse_strict(&canonical).is_ok());
+        for st in &[
+            format!("net:[fe80:0::1]:8008{}", key),
+            format!("net:Host.com:8008{}", key),
+            format!("net:host.com:8008{}.ed25519", key),
+        ] {
+            assert!(MultiserverAddress::from_str(st).is_ok(), "{}", st);
+            match MultiserverAddress::parse_strict(st) {
+                Err(Error::NotCanonical { .. }) => (),
+                other => panic!("{} gave {:?}", st, other),
+            }
+        }
+    }
+    #[test]
+    fn multiserver_leading_garbage_err() {
+        let key = "~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        for (st, offset) in &[
+            (format!("garbage!!net:1.2.3.4:8008{}", key), 0),
+            (format!("xnet:1.2.3.4:8008{}", key), 0),
+            (format!("foo~net:1.2.3.4:8008{}", key), 3),
+        ] {
+            match MultiserverAddress::from_str(st) {
+                Err(Error::Unexpected {
+                    offset: at,
+                    segment: "transport",
+                    ..
+                }) => assert_eq!(at, *offset, "{}", st),
+                other => panic!("{} gave {:?}", st, other),
+            }
+        }
+    }
+    #[test]
+    fn multiserver_parse_lenient_ok() {
+        let key = "~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let expected = MultiserverAddress::from_str(&format!("net:host.com:8008{}", key)).unwrap();
+        let messy = format!("  NET:host.com:8008~SHS:{};\n", &key[5..]);
+        assert!(MultiserverAddress::from_str(&messy).is_err());
+        assert_eq!(MultiserverAddress::parse_lenient(&messy).unwrap(), expected);
+    }
+    #[test]
+    fn multiserver_key_accessors_ok() {
+        let address = MultiserverAddress::loopback(8008, Multikey::from_ed25519(&[3; 32]));
+        assert_eq!(address.pub_key_bytes(), Some([3; 32]));
+        assert_eq!(
+            address.pub_key_base64().unwrap(),
+            base64::encode(&[3; 32][..])
+        );
+        assert_eq!(
+            address.feed_id().unwrap(),
+            format!("@{}.ed25519", base64::encode(&[3; 32][..]))
+        );
+        let noauth = address.as_noauth();
+        assert_eq!(noauth.pub_key_bytes(), None);
+        assert_eq!(noauth.pub_key_base64(), None);
+        assert_eq!(noauth.feed_id(), None);
+    }
+    #[test]
+    fn multiserver_error_variants_ok() {
+        match MultiserverAddress::from_str("net:host.com:8008/path~noauth") {
+            Err(Error::FieldNotAllowed { field: "path", .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        #[cfg(not(feature = "quic"))]
+        match MultiserverAddress::from_str("quic:host.com:443~noauth") {
+            Err(Error::ProtocolUnknown { name }) => assert_eq!(name, "quic"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let err = MultiserverAddress::from_str("net:host.com:8008~shs:not*base64=").unwrap_err();
+        assert!(std::error::Error::source(&err).is_some());
+        let err = "net:host.com:8008~noauth;nope"
+            .parse::<list::MultiserverAddressList>()
+            .unwrap_err();
+        let source = std::error::Error::source(&err).unwrap();
+        assert!(source.to_string().starts_with("Expected "));
+    }
+    #[test]
+    fn multiserver_escaped_data_round_trip_ok() {
+        let dht = MultiserverAddress {
+            transport: Transport::Dht,
+            address: AddressType::Dht("chan;nel~x!y:remote".to_string()),
+            port: 0,
+            path: None,
+            security: vec![SecurityLayer::Noauth],
+        };
+        assert_eq!(dht.to_string(), "dht:chan!;nel!~x!!y:remote~noauth");
+        assert_eq!(MultiserverAddress::from_str(&dht.to_string()).unwrap(), dht);
+
+        let socket = MultiserverAddress::from_str("unix:/tmp/a!;b.sock~noauth").unwrap();
+        let list: list::MultiserverAddressList = format!("{};net:host.com:8008~noauth", socket)
+            .parse()
+            .unwrap();
+        assert_eq!(list[0], socket);
+        assert_eq!(
+            socket.address,
+            AddressType::SocketFilePath("/tmp/a;b.sock".to_string())
+        );
+    }
+    #[cfg(not(feature = "url"))]
+    #[test]
+    fn multiserver_ascii_hostname_ok() {
+        let address = MultiserverAddress::from_str("net:Host.COM:8008~noauth").unwrap();
+        assert_eq!(address.hostname(), Some("host.com"));
+        let err = MultiserverAddress::from_str("net:höflich.example:8008~noauth").unwrap_err();
+        assert!(matches!(err, Error::HostnameInvalid { .. }));
+        assert_eq!(err.kind(), ErrorKind::Semantic);
+    }
+    #[test]
+    fn multiserver_conversions_ok() {
+        use std::convert::TryFrom;
+        let st = "net:127.0.0.1:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::try_from(st).unwrap();
+        assert_eq!(
+            MultiserverAddress::try_from(st.to_string()).unwrap(),
+            address
+        );
+        assert!(MultiserverAddress::try_from("net:host.com").is_err());
+
+        let pub_key = address.pub_key().cloned().unwrap();
+        let socket: SocketAddr = "127.0.0.1:8008".parse().unwrap();
+        assert_eq!(MultiserverAddress::from((socket, pub_key.clone())), address);
+        assert_eq!(
+            MultiserverAddress::from((socket.ip(), 8008, pub_key)),
+            address
+        );
+    }
+    #[test]
+    fn multiserver_unknown_layer_err() {
+        let key = "HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let st = format!("net:1.2.3.4:8008~evil:{}", key);
+        assert!(MultiserverAddress::from_str(&st).is_err());
+
+        let address =
+            MultiserverAddress::from_str(&format!("net:1.2.3.4:8008~shs:{}~noauth", key)).unwrap();
+        assert!(matches!(
+            address.security.as_slice(),
+            [SecurityLayer::Shs(_), SecurityLayer::Noauth]
+        ));
+        assert_eq!(address.pub_key_base64().as_deref(), Some(key));
+        assert!(address.is_noauth());
     }
 }
@@ -0,0 +1,198 @@
+use crate::{split_unescaped, Error, MultiserverAddress, Scope, Transport};
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// A `;`-separated list of addresses, as found in `pub` messages and
+/// `ssb-server` configs. Derefs to a slice for iteration and indexing.
+/// Lists order element by element, like the `Vec` they wrap.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MultiserverAddressList(pub Vec<MultiserverAddress>);
+
+impl MultiserverAddressList {
+    /// Keeps the first address for each value of `key`, preserving order.
+    pub fn dedup_by_key<K: Eq + Hash, F: FnMut(&MultiserverAddress) -> K>(&mut self, mut key: F) {
+        let mut seen = HashSet::new();
+        self.0.retain(|address| seen.insert(key(address)));
+    }
+
+    /// Drops addresses that are semantically equal to an earlier one.
+    pub fn dedup(&mut self) {
+        self.dedup_by_key(MultiserverAddress::canonicalize);
+    }
+
+    /// The entries of a `;`-separated list as written, split at each `;`
+    /// that is not escaped with `!`.
+    pub fn entries(st: &str) -> impl Iterator<Item = &str> {
+        split_unescaped(st, b';').map(|(_, entry)| entry)
+    }
+
+    /// Parses every entry that can be parsed, returning the failures with
+    /// their index instead of rejecting the whole list.
+    pub fn parse_lossy(st: &str) -> (Vec<MultiserverAddress>, Vec<(usize, Error)>) {
+        let mut addresses = Vec::new();
+        let mut errors = Vec::new();
+        for (index, (_, entry)) in split_unescaped(st, b';').enumerate() {
+            match MultiserverAddress::from_str(entry) {
+                Ok(address) => addresses.push(address),
+                Err(err) => errors.push((index, err)),
+            }
+        }
+        (addresses, errors)
+    }
+
+    pub fn filter_by_transport<'a>(
+        &'a self,
+        transport: &'a Transport,
+    ) -> impl Iterator<Item = &'a MultiserverAddress> + 'a {
+        self.0
+            .iter()
+            .filter(move |address| address.transport == *transport)
+    }
+
+    /// The address to dial first: public before local before device scope,
+    /// then `net` before `wss` before `ws` before anything else. Ties go to
+    /// the earlier entry.
+    pub fn best(&self) -> Option<&MultiserverAddress> {
+        self.0.iter().min_by_key(|address| {
+            let scope = match address.scope() {
+                Scope::Public => 0,
+                Scope::Local => 1,
+                Scope::Device => 2,
+            };
+            let transport = match address.transport {
+                Transport::Net => 0,
+                Transport::Wss => 1,
+                Transport::Ws => 2,
+                _ => 3,
+            };
+            (scope, transport)
+        })
+    }
+}
+
+impl FromStr for MultiserverAddressList {
+    type Err = Error;
+
+    fn from_str(st: &str) -> Result<MultiserverAddressList, Error> {
+        split_unescaped(st, b';')
+            .enumerate()
+            .map(|(index, (_, entry))| {
+                MultiserverAddress::from_str(entry).map_err(|source| Error::ListEntryInvalid {
+                    index,
+                    source: Box::new(source),
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(MultiserverAddressList)
+    }
+}
+
+impl fmt::Display for MultiserverAddressList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, address) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(";")?;
+            }
+            address.write_to(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for MultiserverAddressList {
+    type Target = [MultiserverAddress];
+
+    fn deref(&self) -> &[MultiserverAddress] {
+        &self.0
+    }
+}
+
+impl From<Vec<MultiserverAddress>> for MultiserverAddressList {
+    fn from(addresses: Vec<MultiserverAddress>) -> MultiserverAddressList {
+        MultiserverAddressList(addresses)
+    }
+}
+
+impl IntoIterator for MultiserverAddressList {
+    type Item = MultiserverAddress;
+    type IntoIter = std::vec::IntoIter<MultiserverAddress>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MultiserverAddressList {
+    type Item = &'a MultiserverAddress;
+    type IntoIter = std::slice::Iter<'a, MultiserverAddress>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=;https:room.example.com/alias~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+
+    #[test]
+    fn list_round_trip_ok() {
+        let list = MultiserverAddressList::from_str(LIST).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1].port, 443);
+        assert_eq!(list.iter().filter(|a| a.path.is_some()).count(), 1);
+        assert_eq!(list.to_string(), LIST);
+    }
+    #[test]
+    fn list_dedup_ok() {
+        let mut list = MultiserverAddressList::from_str(&format!(
+            "{};net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            LIST
+        ))
+        .unwrap();
+        list.dedup();
+        assert_eq!(list.len(), 2);
+        list.dedup_by_key(|address| address.pub_key().cloned());
+        assert_eq!(list.len(), 1);
+    }
+    #[test]
+    fn list_best_ok() {
+        let list = MultiserverAddressList::from_str(&format!(
+            "{};ws:host.com:8989~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=;net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            LIST
+        ))
+        .unwrap();
+        assert_eq!(list.best(), Some(&list[3]));
+        assert_eq!(list.filter_by_transport(&Transport::Ws).count(), 1);
+    }
+    #[test]
+    fn list_entry_invalid_err() {
+        let input = format!("{};not an address", LIST);
+        match MultiserverAddressList::from_str(&input) {
+            Err(err @ Error::ListEntryInvalid { index: 2, .. }) => assert!(err.is_syntax()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+    #[test]
+    fn list_parse_lossy_ok() {
+        let input = format!("net:old.pub:8008:@key.ed25519;{}", LIST);
+        assert!(MultiserverAddressList::from_str(&input).is_err());
+        let (addresses, errors) = MultiserverAddressList::parse_lossy(&input);
+        assert_eq!(addresses, MultiserverAddressList::from_str(LIST).unwrap().0);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 0);
+    }
+    #[test]
+    fn list_entries_ok() {
+        let entries: Vec<&str> =
+            MultiserverAddressList::entries("unix:/tmp/a!;b.sock;net:host.com:8008").collect();
+        assert_eq!(entries, ["unix:/tmp/a!;b.sock", "net:host.com:8008"]);
+        let socket = MultiserverAddress::from_str(entries[0]).unwrap();
+        assert_eq!(socket.host_text(), "/tmp/a;b.sock");
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{Error, MultiserverAddress};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Memory-maps a newline-separated peer dump and parses it in parallel on the
+/// rayon thread pool. Results are yielded in file order, one per non-blank
+/// line. The file must not be modified while it is loaded: changes to a mapped
+/// file underneath the parser are undefined behaviour.
+pub fn load_peer_file<P: AsRef<Path>>(
+    path: P,
+) -> io::Result<impl Iterator<Item = Result<MultiserverAddress, Error>>> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(Vec::new().into_iter().flatten());
+    }
+
+    let mmap = unsafe { Mmap::map(&file)? };
+    let text = std::str::from_utf8(&mmap)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    let parsed: Vec<Vec<Result<MultiserverAddress, Error>>> =
+        split_lines(text, rayon::current_num_threads() * 4)
+            .par_iter()
+            .map(|chunk| {
+                chunk
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(MultiserverAddress::from_str)
+                    .collect()
+            })
+            .collect();
+
+    Ok(parsed.into_iter().flatten())
+}
+
+// Splits `text` into roughly `count` chunks, each ending on a line boundary.
+fn split_lines(text: &str, count: usize) -> Vec<&str> {
+    let target = text.len() / count.max(1) + 1;
+    let mut chunks = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let end = rest
+            .as_bytes()
+            .iter()
+            .skip(target)
+            .position(|b| *b == b'\n')
+            .map_or(rest.len(), |i| target + i + 1);
+        chunks.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn split_lines_ok() {
+        let text = "a\nbb\nccc\ndddd\n";
+        let chunks = split_lines(text, 3);
+        assert_eq!(chunks.concat(), text);
+        assert!(chunks.iter().all(|chunk| chunk.ends_with('\n')));
+    }
+    #[test]
+    fn load_peer_file_ok() {
+        let path = std::env::temp_dir().join(format!("msaddr-loader-{}.txt", std::process::id()));
+        let lines: Vec<String> = (0..100)
+            .map(|i| {
+                format!(
+                    "net:10.0.0.{}:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+                    i
+                )
+            })
+            .collect();
+        fs::write(&path, format!("{}\n\nnot an address\n", lines.join("\n"))).unwrap();
+
+        let results: Vec<_> = load_peer_file(&path).unwrap().collect();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(results.len(), 101);
+        assert_eq!(results[42].as_ref().unwrap(), &lines[42].as_str());
+        assert!(results[100].is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{AddressType, CidrInvalid, Error, MultiserverAddress};
+use snafu::OptionExt;
+use ssb_multiformats::multikey::Multikey;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Matches addresses against CIDR ranges, hostname suffixes and public keys.
+/// Ranges are bucketed by prefix length and hostnames are checked label by
+/// label, so lookups cost a handful of hash probes regardless of list size.
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    v4: HashMap<u8, HashSet<u32>>,
+    v6: HashMap<u8, HashSet<u128>>,
+    host_suffixes: HashSet<String>,
+    pub_keys: HashSet<Multikey>,
+}
+
+impl Matcher {
+    pub fn new() -> Matcher {
+        Matcher::default()
+    }
+
+    /// Accepts `1.2.3.0/24`, `fe80::/10`, or a bare IP as a single-host range.
+    pub fn add_cidr(&mut self, cidr: &str) -> Result<(), Error> {
+        let mut parts = cidr.splitn(2, '/');
+        let ip = parts
+            .next()
+            .and_then(|ip| IpAddr::from_str(ip.trim()).ok())
+            .context(CidrInvalid)?;
+        let max = match ip {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match parts.next() {
+            Some(prefix) => u8::from_str(prefix.trim()).ok().context(CidrInvalid)?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(Error::CidrInvalid {});
+        }
+
+        match ip {
+            IpAddr::V4(ip) => {
+                let network = mask_v4(u32::from(ip), prefix);
+                self.v4.entry(prefix).or_default().insert(network);
+            }
+            IpAddr::V6(ip) => {
+                let network = mask_v6(u128::from(ip), prefix);
+                self.v6.entry(prefix).or_default().insert(network);
+            }
+        }
+        Ok(())
+    }
+
+    /// `example.com` matches `example.com` and every subdomain of it.
+    pub fn add_host_suffix(&mut self, suffix: &str) {
+        let suffix = suffix.trim_start_matches('.').to_lowercase();
+        self.host_suffixes.insert(suffix);
+    }
+
+    pub fn add_pub_key(&mut self, pub_key: Multikey) {
+        self.pub_keys.insert(pub_key);
+    }
+
+    /// A tunnel address matches when its portal or target key is listed, so
+    /// a blocked peer cannot be reached through a room either.
+    pub fn matches(&self, address: &MultiserverAddress) -> bool {
+        let key_matches = [
+            address.pub_key(),
+            address.portal_key(),
+            address.target_key(),
+        ]
+        .iter()
+        .flatten()
+        .any(|pub_key| self.pub_keys.contains(*pub_key));
+
+        key_matches
+            || match &address.address {
+                AddressType::Ip(ip) => self.matches_ip(*ip),
+                AddressType::Hostname(host) => self.matches_host(host),
+                _ => false,
+            }
+    }
+
+    /// Reads better at call sites when the matcher was loaded from a blocklist.
+    pub fn blocks(&self, address: &MultiserverAddress) -> bool {
+        self.matches(address)
+    }
+
+    // IPv4-mapped IPv6 addresses are checked against the IPv4 ranges, or
+    // `::ffff:10.0.0.1` would slip past a `10.0.0.0/8` entry.
+    fn matches_ip(&self, ip: IpAddr) -> bool {
+        match crate::unmap_ip(ip) {
+            IpAddr::V4(ip) => {
+                let ip = u32::from(ip);
+                self.v4
+                    .iter()
+                    .any(|(prefix, networks)| networks.contains(&mask_v4(ip, *prefix)))
+            }
+            IpAddr::V6(ip) => {
+                let ip = u128::from(ip);
+                self.v6
+                    .iter()
+                    .any(|(prefix, networks)| networks.contains(&mask_v6(ip, *prefix)))
+            }
+        }
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        let mut rest = host.as_str();
+        loop {
+            if self.host_suffixes.contains(rest) {
+                return true;
+            }
+            match rest.find('.') {
+                Some(i) => rest = &rest[i + 1..],
+                None => return false,
+            }
+        }
+    }
+}
+
+fn mask_v4(ip: u32, prefix: u8) -> u32 {
+    match prefix {
+        0 => 0,
+        prefix => ip & (u32::MAX << (32 - prefix)),
+    }
+}
+
+fn mask_v6(ip: u128, prefix: u8) -> u128 {
+    match prefix {
+        0 => 0,
+        prefix => ip & (u128::MAX << (128 - prefix)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(st: &str) -> MultiserverAddress {
+        MultiserverAddress::from_str(st).unwrap()
+    }
+
+    #[test]
+    fn matcher_cidr_ok() {
+        let mut matcher = Matcher::new();
+        matcher.add_cidr("192.168.0.0/16").unwrap();
+        matcher.add_cidr("fe80::/10").unwrap();
+
+        assert!(matcher.blocks(&parse(
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )));
+        assert!(matcher.blocks(&parse(
+            "net:FE80:0000:0000:0000:0202:B3FF:FE1E:8329:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )));
+        assert!(!matcher.blocks(&parse(
+            "net:10.0.0.1:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )));
+    }
+    #[test]
+    fn matcher_cidr_err() {
+        let mut matcher = Matcher::new();
+        assert!(matcher.add_cidr("10.0.0.0/33").is_err());
+        assert!(matcher.add_cidr("not-an-ip/8").is_err());
+        assert!(matcher.add_cidr("0.0.0.0/0").is_ok());
+        assert!(matcher.blocks(&parse(
+            "net:10.0.0.1:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )));
+    }
+    #[test]
+    fn matcher_host_and_key_ok() {
+        let mut matcher = Matcher::new();
+        matcher.add_host_suffix("example.com");
+        assert!(matcher.blocks(&parse(
+            "net:pub.Example.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )));
+        assert!(!matcher.blocks(&parse(
+            "net:notexample.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )));
+
+        let address = parse("net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=");
+        matcher.add_pub_key(address.pub_key().cloned().unwrap());
+        assert!(matcher.blocks(&address));
+    }
+    #[test]
+    fn matcher_mapped_ip_ok() {
+        let mut matcher = Matcher::new();
+        matcher.add_cidr("10.0.0.0/8").unwrap();
+        assert!(matcher.blocks(&parse(
+            "net:[::ffff:10.0.0.1]:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+        )));
+    }
+    #[test]
+    fn matcher_tunnel_keys_ok() {
+        let mut matcher = Matcher::new();
+        let address = parse("tunnel:@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519:@AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.ed25519~shs:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=");
+        assert!(!matcher.blocks(&address));
+
+        matcher.add_pub_key(address.portal_key().cloned().unwrap());
+        assert!(matcher.blocks(&address));
+
+        let mut matcher = Matcher::new();
+        matcher.add_pub_key(address.target_key().cloned().unwrap());
+        assert!(matcher.blocks(&address));
+    }
+}
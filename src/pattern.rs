@@ -0,0 +1,252 @@
+use crate::{
+    address_from_host, default_port, grammar, pub_key_base64, split_unescaped, unescape,
+    AddressType, Error, MultiserverAddress, PatternInvalid,
+};
+use snafu::OptionExt;
+use std::str::FromStr;
+
+/// A multiserver address where any field may contain `*` (any run of
+/// characters) or `?` (a single character), e.g. `net:*.example.com:8008~shs:*`.
+/// Leaving out the `~shs:` part matches addresses with any key or none, and
+/// leaving out the path matches any path. A `~noauth` layer matches only
+/// addresses that have one. Fields without wildcards are read like the same
+/// field of an address, so hosts are normalized, IPv6 hosts may be bracketed
+/// and web transports may leave out their default port.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AddressPattern {
+    protocol: String,
+    host: String,
+    port: String,
+    path: Option<String>,
+    pub_key: Option<String>,
+    noauth: bool,
+}
+
+impl AddressPattern {
+    pub fn parse(pattern: &str) -> Result<AddressPattern, Error> {
+        let mut layers = split_unescaped(pattern, b'~').map(|(_, layer)| layer);
+        let transport = layers.next().context(PatternInvalid)?;
+        let (protocol, data) = split_first(transport, ':').context(PatternInvalid)?;
+        let (data, path) = match data.find('/') {
+            Some(i) if grammar::allows_path(protocol) => (&data[..i], Some(data[i..].to_string())),
+            _ => (data, None),
+        };
+        let (host, port) = host_port(protocol, data)?;
+
+        let mut pub_key = None;
+        let mut noauth = false;
+        for layer in layers {
+            match split_first(layer, ':') {
+                Some(("shs", key)) if pub_key.is_none() => pub_key = Some(key.to_string()),
+                None if layer == "noauth" && !noauth => noauth = true,
+                _ => return Err(Error::PatternInvalid {}),
+            }
+        }
+
+        Ok(AddressPattern {
+            protocol: protocol.to_string(),
+            host,
+            port,
+            path,
+            pub_key,
+            noauth,
+        })
+    }
+
+    pub fn matches(&self, address: &MultiserverAddress) -> bool {
+        let (protocol, host) = match &address.address {
+            AddressType::Ip(ip) => (address.transport.as_str(), ip.to_string()),
+            AddressType::Hostname(host) => (address.transport.as_str(), host.to_lowercase()),
+            AddressType::SocketFilePath(path) => ("unix", path.clone()),
+            AddressType::WebRtc(signal) => ("webrtc", signal.clone()),
+            AddressType::Bluetooth(device) => ("bt", device.clone()),
+            AddressType::Dht(channel) => ("dht", channel.clone()),
+            AddressType::Tunnel { portal, target } => (
+                "tunnel",
+                format!(
+                    "{}:{}",
+                    portal.to_legacy_string(),
+                    target.to_legacy_string()
+                ),
+            ),
+        };
+        let pub_key_matches = match (&self.pub_key, &address.pub_key()) {
+            (None, _) => true,
+            (Some(pattern), Some(pub_key)) => glob(pattern, &pub_key_base64(pub_key)),
+            (Some(_), None) => false,
+        };
+        let path_matches = match &self.path {
+            None => true,
+            Some(pattern) => glob(pattern, address.path.as_deref().unwrap_or_default()),
+        };
+
+        glob(&self.protocol, protocol)
+            && glob(&self.host, &host)
+            && glob(&self.port, &address.port.to_string())
+            && path_matches
+            && pub_key_matches
+            && (!self.noauth || address.is_noauth())
+    }
+}
+
+// Splits the data of the transport layer into host and port patterns. The
+// transports without a port match any port.
+fn host_port(protocol: &str, data: &str) -> Result<(String, String), Error> {
+    if matches!(protocol, "unix" | "bt" | "dht" | "webrtc" | "tunnel") {
+        return Ok((unescape(data), "*".to_string()));
+    }
+    let (host, port) = match data.strip_prefix('[') {
+        Some(rest) => {
+            let end = rest.find(']').context(PatternInvalid)?;
+            let port = match &rest[end + 1..] {
+                "" => None,
+                port => Some(port.strip_prefix(':').context(PatternInvalid)?),
+            };
+            (&rest[..end], port)
+        }
+        // Hosts may be IPv6 literals, so the port is whatever follows the
+        // last colon.
+        None => match split_last(data, ':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (data, None),
+        },
+    };
+
+    let port = match port {
+        Some(port) if is_glob(port) => port.to_string(),
+        Some(port) => u16::from_str(port)
+            .ok()
+            .context(PatternInvalid)?
+            .to_string(),
+        None => default_port(protocol).context(PatternInvalid)?.to_string(),
+    };
+    let host = if is_glob(host) {
+        host.to_lowercase()
+    } else {
+        match address_from_host(host)? {
+            AddressType::Ip(ip) => ip.to_string(),
+            AddressType::Hostname(host) => host,
+            _ => return Err(Error::PatternInvalid {}),
+        }
+    };
+    Ok((host, port))
+}
+
+fn is_glob(st: &str) -> bool {
+    st.contains(['*', '?'])
+}
+
+fn split_first(st: &str, separator: char) -> Option<(&str, &str)> {
+    st.find(separator).map(|i| (&st[..i], &st[i + 1..]))
+}
+
+fn split_last(st: &str, separator: char) -> Option<(&str, &str)> {
+    st.rfind(separator).map(|i| (&st[..i], &st[i + 1..]))
+}
+
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text index it was tried at.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        // `*` is tried first, so that a literal `*` in the text does not
+        // consume it without leaving a point to backtrack to.
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn pattern_matches_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:pub.example.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+
+        assert!(AddressPattern::parse("net:*.example.com:8008~shs:*")
+            .unwrap()
+            .matches(&address));
+        assert!(AddressPattern::parse("*:*:80??").unwrap().matches(&address));
+        assert!(!AddressPattern::parse("net:*.example.org:*")
+            .unwrap()
+            .matches(&address));
+        assert!(!AddressPattern::parse("net:*:8008~shs:AAAA*")
+            .unwrap()
+            .matches(&address));
+    }
+    #[test]
+    fn pattern_ipv6_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:FE80:0000:0000:0000:0202:B3FF:FE1E:8329:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert!(AddressPattern::parse("net:fe80::*:8008")
+            .unwrap()
+            .matches(&address));
+    }
+    #[test]
+    fn pattern_address_forms_ok() {
+        let hub = MultiserverAddress::from_str(
+            "wss:Hub.example.com/ssb~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        assert!(AddressPattern::parse("wss:hub.example.com~shs:*")
+            .unwrap()
+            .matches(&hub));
+        assert!(AddressPattern::parse("wss:HUB.example.com:443/ssb")
+            .unwrap()
+            .matches(&hub));
+        assert!(!AddressPattern::parse("wss:hub.example.com/other")
+            .unwrap()
+            .matches(&hub));
+        assert!(!AddressPattern::parse("*:*:*~noauth").unwrap().matches(&hub));
+
+        let local = MultiserverAddress::from_str("net:[::1]:8008~noauth").unwrap();
+        assert!(AddressPattern::parse("net:[::1]:8008~noauth")
+            .unwrap()
+            .matches(&local));
+        assert!(AddressPattern::parse("net:[0:0:0:0:0:0:0:1]:*")
+            .unwrap()
+            .matches(&local));
+
+        let socket = MultiserverAddress::from_str("unix:/tmp/a!;b.sock~noauth").unwrap();
+        assert!(AddressPattern::parse("unix:/tmp/a!;*~noauth")
+            .unwrap()
+            .matches(&socket));
+
+        let star = MultiserverAddress::from_str("unix:/tmp/*x").unwrap();
+        assert!(AddressPattern::parse("unix:/tmp/*").unwrap().matches(&star));
+        assert!(AddressPattern::parse("unix:/tmp/*x")
+            .unwrap()
+            .matches(&star));
+    }
+    #[test]
+    fn pattern_parse_err() {
+        assert!(AddressPattern::parse("net").is_err());
+        assert!(AddressPattern::parse("net:host").is_err());
+        assert!(AddressPattern::parse("net:host:80800").is_err());
+        assert!(AddressPattern::parse("net:host:8008~foo").is_err());
+        assert!(AddressPattern::parse("net:host:8008~noauth~noauth").is_err());
+    }
+}
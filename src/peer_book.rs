@@ -0,0 +1,227 @@
+use crate::MultiserverAddress;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version `save` writes. `load` reads this and every earlier version.
+pub const PEER_BOOK_VERSION: u32 = 1;
+
+/// What is known about one address. Times are seconds since the Unix epoch.
+/// Missing fields load as their defaults, so records written before a field
+/// was added still load.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerRecord {
+    pub last_seen: Option<u64>,
+    pub last_success: Option<u64>,
+    /// Failed attempts since the last success.
+    pub failures: u32,
+}
+
+/// Addresses with their connection history, persisted as a JSON object with
+/// the format `version` and a `peers` object that maps each canonical address
+/// string to its `PeerRecord`. Spellings of the same address share one record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerBook {
+    peers: BTreeMap<String, PeerRecord>,
+}
+
+#[derive(Serialize)]
+struct StoredRef<'a> {
+    version: u32,
+    peers: &'a BTreeMap<String, PeerRecord>,
+}
+
+// Books from before the format was versioned are the bare `peers` object.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Stored {
+    Versioned {
+        version: u32,
+        peers: BTreeMap<String, PeerRecord>,
+    },
+    Unversioned(BTreeMap<String, PeerRecord>),
+}
+
+impl PeerBook {
+    pub fn new() -> PeerBook {
+        PeerBook::default()
+    }
+
+    /// Reads a book saved by `save` of this or an earlier release. A missing
+    /// file is an empty book, entries whose key no longer parses are dropped,
+    /// and a version newer than `PEER_BOOK_VERSION` is `InvalidData`.
+    pub fn load(path: &Path) -> io::Result<PeerBook> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(PeerBook::new()),
+            Err(err) => return Err(err),
+        };
+        let stored: Stored = serde_json::from_str(&json)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let peers = match stored {
+            Stored::Versioned { version, .. } if version > PEER_BOOK_VERSION => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("peer book version {} is not supported", version),
+                ))
+            }
+            Stored::Versioned { peers, .. } | Stored::Unversioned(peers) => peers,
+        };
+        let peers = peers
+            .into_iter()
+            .filter_map(|(address, record)| {
+                let address = MultiserverAddress::from_str(&address).ok()?;
+                Some((key(&address), record))
+            })
+            .collect();
+        Ok(PeerBook { peers })
+    }
+
+    /// Writes the book to a temporary file next to `path` and renames it
+    /// into place, so readers never see a half-written book.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let stored = StoredRef {
+            version: PEER_BOOK_VERSION,
+            peers: &self.peers,
+        };
+        let json = serde_json::to_vec_pretty(&stored)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+    pub fn get(&self, address: &MultiserverAddress) -> Option<&PeerRecord> {
+        self.peers.get(&key(address))
+    }
+
+    /// Notes that `address` was advertised or otherwise heard of at `at`.
+    pub fn record_seen(&mut self, address: &MultiserverAddress, at: SystemTime) {
+        self.entry(address).last_seen = Some(unix_secs(at));
+    }
+
+    /// Notes a successful connection at `at`, which also resets the failure
+    /// count.
+    pub fn record_success(&mut self, address: &MultiserverAddress, at: SystemTime) {
+        let record = self.entry(address);
+        record.last_success = Some(unix_secs(at));
+        record.failures = 0;
+    }
+
+    pub fn record_failure(&mut self, address: &MultiserverAddress) {
+        let record = self.entry(address);
+        record.failures = record.failures.saturating_add(1);
+    }
+
+    pub fn remove(&mut self, address: &MultiserverAddress) -> Option<PeerRecord> {
+        self.peers.remove(&key(address))
+    }
+
+    /// Every address with its record, in canonical string order.
+    pub fn iter(&self) -> impl Iterator<Item = (MultiserverAddress, &PeerRecord)> {
+        self.peers.iter().filter_map(|(address, record)| {
+            Some((MultiserverAddress::from_str(address).ok()?, record))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    fn entry(&mut self, address: &MultiserverAddress) -> &mut PeerRecord {
+        self.peers.entry(key(address)).or_default()
+    }
+}
+
+fn key(address: &MultiserverAddress) -> String {
+    address.canonicalize().to_string()
+}
+
+// Times before the epoch are clamped to it.
+fn unix_secs(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn address(st: &str) -> MultiserverAddress {
+        MultiserverAddress::from_str(st).unwrap()
+    }
+
+    #[test]
+    fn peer_book_record_ok() {
+        let at = UNIX_EPOCH + Duration::from_secs(1000);
+        let mut book = PeerBook::new();
+        book.record_seen(&address("net:Host.com.:8008~noauth"), at);
+        book.record_failure(&address("net:host.com:8008~noauth"));
+        book.record_failure(&address("net:host.com:8008~noauth"));
+
+        let record = book.get(&address("net:host.com:8008~noauth")).unwrap();
+        assert_eq!(book.len(), 1);
+        assert_eq!(record.last_seen, Some(1000));
+        assert_eq!(record.failures, 2);
+
+        book.record_success(&address("net:host.com:8008~noauth"), at);
+        let record = book.get(&address("net:host.com:8008~noauth")).unwrap();
+        assert_eq!(record.failures, 0);
+        assert_eq!(record.last_success, Some(1000));
+    }
+    #[test]
+    fn peer_book_save_load_ok() {
+        let path = std::env::temp_dir().join(format!("msaddr-peers-{}.json", std::process::id()));
+        assert_eq!(PeerBook::load(&path).unwrap(), PeerBook::new());
+
+        let mut book = PeerBook::new();
+        book.record_seen(
+            &address("net:1.2.3.4:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="),
+            UNIX_EPOCH,
+        );
+        book.record_failure(&address("wss:room.example.com/ssb~noauth"));
+        book.save(&path).unwrap();
+
+        let loaded = PeerBook::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, book);
+        assert_eq!(loaded.iter().count(), 2);
+    }
+    #[test]
+    fn peer_book_load_versions_ok() {
+        let path =
+            std::env::temp_dir().join(format!("msaddr-versions-{}.json", std::process::id()));
+
+        fs::write(&path, r#"{"net:Host.com:8008~noauth": {"failures": 3}}"#).unwrap();
+        let record = PeerBook::load(&path)
+            .unwrap()
+            .get(&address("net:host.com:8008~noauth"))
+            .cloned();
+        assert_eq!(
+            record,
+            Some(PeerRecord {
+                failures: 3,
+                ..PeerRecord::default()
+            })
+        );
+
+        fs::write(&path, r#"{"version": 99, "peers": {}}"#).unwrap();
+        let err = PeerBook::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
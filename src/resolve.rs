@@ -0,0 +1,131 @@
+use crate::{AddressType, MultiserverAddress, Transport};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+
+/// Which address family `resolve` lists first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpPreference {
+    /// Keep the resolver's order.
+    Any,
+    PreferV4,
+    PreferV6,
+}
+
+impl MultiserverAddress {
+    /// Looks up a hostname through the system resolver and returns one
+    /// IP-based copy of this address per result, keeping transport, port and
+    /// key. IP addresses resolve to themselves.
+    ///
+    /// Only `net` and `udp` addresses are resolved. Web transports need the
+    /// hostname to connect, for TLS server names and `Host` headers, so they
+    /// fail with `InvalidInput`, as do transports without a network host.
+    pub fn resolve(&self, preference: IpPreference) -> io::Result<Vec<MultiserverAddress>> {
+        self.check_resolvable()?;
+        let addrs = self.to_socket_addrs()?.collect();
+        Ok(self.with_resolved(addrs, preference))
+    }
+
+    /// Like `resolve`, but looks hostnames up on the tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub async fn resolve_async(
+        &self,
+        preference: IpPreference,
+    ) -> io::Result<Vec<MultiserverAddress>> {
+        self.check_resolvable()?;
+        let addrs = match &self.address {
+            AddressType::Hostname(host) => tokio::net::lookup_host((host.as_str(), self.port))
+                .await?
+                .collect(),
+            _ => self.to_socket_addrs()?.collect(),
+        };
+        Ok(self.with_resolved(addrs, preference))
+    }
+
+    /// Like `resolve`, but looks hostnames up on the async-std runtime.
+    #[cfg(feature = "async-std")]
+    pub async fn resolve_async_std(
+        &self,
+        preference: IpPreference,
+    ) -> io::Result<Vec<MultiserverAddress>> {
+        self.check_resolvable()?;
+        let addrs = match &self.address {
+            AddressType::Hostname(host) => {
+                async_std::net::ToSocketAddrs::to_socket_addrs(&(host.as_str(), self.port))
+                    .await?
+                    .collect()
+            }
+            _ => self.to_socket_addrs()?.collect(),
+        };
+        Ok(self.with_resolved(addrs, preference))
+    }
+
+    fn check_resolvable(&self) -> io::Result<()> {
+        match self.transport {
+            Transport::Net | Transport::Udp => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} addresses are not resolved", self.transport.as_str()),
+            )),
+        }
+    }
+
+    fn with_resolved(
+        &self,
+        mut addrs: Vec<SocketAddr>,
+        preference: IpPreference,
+    ) -> Vec<MultiserverAddress> {
+        match preference {
+            IpPreference::Any => (),
+            IpPreference::PreferV4 => addrs.sort_by_key(SocketAddr::is_ipv6),
+            IpPreference::PreferV6 => addrs.sort_by_key(SocketAddr::is_ipv4),
+        }
+        addrs
+            .into_iter()
+            .map(|addr| MultiserverAddress {
+                address: AddressType::Ip(addr.ip()),
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IpPreference;
+    use crate::{AddressType, MultiserverAddress};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::str::FromStr;
+
+    #[test]
+    fn resolve_hostname_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:localhost:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let resolved = address.resolve(IpPreference::PreferV4).unwrap();
+        assert_eq!(
+            resolved[0].address,
+            AddressType::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        );
+        assert!(resolved
+            .iter()
+            .all(|resolved| resolved.port == 8008 && resolved.pub_key() == address.pub_key()));
+    }
+
+    #[test]
+    fn resolve_ip_ok() {
+        let address = MultiserverAddress::from_str("net:[::1]:8008~noauth").unwrap();
+        assert_eq!(
+            address.resolve(IpPreference::Any).unwrap(),
+            vec![address.clone()]
+        );
+    }
+
+    #[test]
+    fn resolve_err() {
+        let socket = MultiserverAddress::from_str("unix:/tmp/ssb.sock").unwrap();
+        assert!(socket.resolve(IpPreference::Any).is_err());
+        let wss = MultiserverAddress::from_str("wss:localhost~noauth").unwrap();
+        assert!(wss.resolve(IpPreference::Any).is_err());
+    }
+}
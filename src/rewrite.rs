@@ -0,0 +1,133 @@
+use crate::{address_from_host, AddressType, Error, MultiserverAddress};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    from: AddressType,
+    from_port: Option<u16>,
+    to: AddressType,
+    to_port: Option<u16>,
+}
+
+/// Host/port mappings between the internal and external view of a NAT.
+/// Rules are tried in insertion order and the first match wins. Transport,
+/// key and path are never touched.
+#[derive(Debug, Clone, Default)]
+pub struct RewriteMap {
+    rules: Vec<Rule>,
+}
+
+impl RewriteMap {
+    pub fn new() -> RewriteMap {
+        RewriteMap::default()
+    }
+
+    /// Maps `from_host` (on `from_port`, or any port if `None`) to `to_host`
+    /// (on `to_port`, or the original port if `None`). Hosts are IP literals
+    /// or hostnames.
+    pub fn add(
+        &mut self,
+        from_host: &str,
+        from_port: Option<u16>,
+        to_host: &str,
+        to_port: Option<u16>,
+    ) -> Result<&mut RewriteMap, Error> {
+        self.rules.push(Rule {
+            from: address_from_host(from_host)?,
+            from_port,
+            to: address_from_host(to_host)?,
+            to_port,
+        });
+        Ok(self)
+    }
+
+    /// The reverse mapping, e.g. for hairpinning external addresses back to
+    /// internal ones.
+    pub fn inverse(&self) -> RewriteMap {
+        let rules = self
+            .rules
+            .iter()
+            .map(|rule| Rule {
+                from: rule.to.clone(),
+                from_port: rule.to_port.or(rule.from_port),
+                to: rule.from.clone(),
+                to_port: rule.to_port.and(rule.from_port),
+            })
+            .collect();
+        RewriteMap { rules }
+    }
+
+    pub fn apply(&self, address: &MultiserverAddress) -> MultiserverAddress {
+        let rule = self.rules.iter().find(|rule| {
+            rule.from == address.address && rule.from_port.is_none_or(|port| port == address.port)
+        });
+        match rule {
+            Some(rule) => MultiserverAddress {
+                address: rule.to.clone(),
+                port: rule.to_port.unwrap_or(address.port),
+                ..address.clone()
+            },
+            None => address.clone(),
+        }
+    }
+
+    pub fn apply_all(&self, addresses: &[MultiserverAddress]) -> Vec<MultiserverAddress> {
+        addresses
+            .iter()
+            .map(|address| self.apply(address))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const KEY: &str = "~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+
+    fn address(host_port: &str) -> MultiserverAddress {
+        MultiserverAddress::from_str(&format!("net:{}{}", host_port, KEY)).unwrap()
+    }
+
+    #[test]
+    fn rewrite_map_ok() {
+        let mut map = RewriteMap::new();
+        map.add("192.168.1.10", Some(8008), "pub.example.com", Some(18008))
+            .unwrap()
+            .add("10.0.0.1", None, "203.0.113.7", None)
+            .unwrap();
+
+        let rewritten = map.apply_all(&[
+            address("192.168.1.10:8008"),
+            address("192.168.1.10:9000"),
+            address("10.0.0.1:1234"),
+        ]);
+        assert_eq!(
+            rewritten,
+            vec![
+                address("pub.example.com:18008"),
+                address("192.168.1.10:9000"),
+                address("203.0.113.7:1234"),
+            ]
+        );
+        assert_eq!(rewritten[0].pub_key(), address("10.0.0.1:1").pub_key());
+    }
+    #[test]
+    fn rewrite_map_inverse_ok() {
+        let mut map = RewriteMap::new();
+        map.add("192.168.1.10", Some(8008), "pub.example.com", Some(18008))
+            .unwrap()
+            .add("10.0.0.1", None, "203.0.113.7", None)
+            .unwrap();
+        let inverse = map.inverse();
+
+        assert_eq!(
+            inverse.apply(&address("pub.example.com:18008")),
+            address("192.168.1.10:8008")
+        );
+        assert_eq!(
+            inverse.apply(&address("203.0.113.7:1234")),
+            address("10.0.0.1:1234")
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::MultiserverAddress;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+struct Entry {
+    address: MultiserverAddress,
+    failures: u32,
+    next_attempt: Instant,
+}
+
+/// Tracks dial attempts per address and answers which address to try next
+/// and when. Every consecutive failure doubles an address's delay, starting
+/// at `base` and capped at `max`; a success clears it.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    entries: Vec<Entry>,
+    base: Duration,
+    max: Duration,
+}
+
+impl Scheduler {
+    pub fn new(base: Duration, max: Duration) -> Scheduler {
+        Scheduler {
+            entries: Vec::new(),
+            base,
+            max,
+        }
+    }
+
+    /// New addresses may be dialed at `now`. Adding a known address is a no-op.
+    pub fn add(&mut self, address: MultiserverAddress, now: Instant) {
+        if self.position(&address).is_none() {
+            self.entries.push(Entry {
+                address,
+                failures: 0,
+                next_attempt: now,
+            });
+        }
+    }
+
+    pub fn remove(&mut self, address: &MultiserverAddress) {
+        self.entries.retain(|entry| entry.address != *address);
+    }
+
+    /// The address with the earliest allowed attempt, and that time. Ties go
+    /// to the address with fewer failures, then to the one added first.
+    pub fn next(&self) -> Option<(&MultiserverAddress, Instant)> {
+        self.entries
+            .iter()
+            .min_by_key(|entry| (entry.next_attempt, entry.failures))
+            .map(|entry| (&entry.address, entry.next_attempt))
+    }
+
+    /// Addresses whose backoff has expired at `now`.
+    pub fn due(&self, now: Instant) -> impl Iterator<Item = &MultiserverAddress> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.next_attempt <= now)
+            .map(|entry| &entry.address)
+    }
+
+    pub fn record_failure(&mut self, address: &MultiserverAddress, now: Instant) {
+        let (base, max) = (self.base, self.max);
+        if let Some(entry) = self.entry_mut(address) {
+            entry.failures = entry.failures.saturating_add(1);
+            let delay = base
+                .checked_mul(2u32.saturating_pow(entry.failures - 1))
+                .map_or(max, |delay| delay.min(max));
+            entry.next_attempt = now + delay;
+        }
+    }
+
+    pub fn record_success(&mut self, address: &MultiserverAddress, now: Instant) {
+        if let Some(entry) = self.entry_mut(address) {
+            entry.failures = 0;
+            entry.next_attempt = now;
+        }
+    }
+
+    pub fn failures(&self, address: &MultiserverAddress) -> Option<u32> {
+        self.position(address).map(|i| self.entries[i].failures)
+    }
+
+    fn position(&self, address: &MultiserverAddress) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.address == *address)
+    }
+
+    fn entry_mut(&mut self, address: &MultiserverAddress) -> Option<&mut Entry> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.address == *address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn address(port: u16) -> MultiserverAddress {
+        MultiserverAddress::from_str(&format!(
+            "net:host.com:{}~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            port
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn scheduler_backoff_ok() {
+        let now = Instant::now();
+        let mut scheduler = Scheduler::new(Duration::from_secs(1), Duration::from_secs(5));
+        scheduler.add(address(1), now);
+        scheduler.add(address(2), now);
+
+        scheduler.record_failure(&address(1), now);
+        assert_eq!(scheduler.next(), Some((&address(2), now)));
+        assert_eq!(scheduler.due(now).count(), 1);
+
+        scheduler.record_failure(&address(2), now);
+        scheduler.record_failure(&address(2), now);
+        assert_eq!(
+            scheduler.next(),
+            Some((&address(1), now + Duration::from_secs(1)))
+        );
+
+        for _ in 0..10 {
+            scheduler.record_failure(&address(2), now);
+        }
+        assert_eq!(scheduler.failures(&address(2)), Some(12));
+        assert_eq!(scheduler.due(now + Duration::from_secs(5)).count(), 2);
+
+        scheduler.record_success(&address(2), now);
+        assert_eq!(scheduler.next(), Some((&address(2), now)));
+    }
+}
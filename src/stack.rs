@@ -0,0 +1,267 @@
+use crate::events::{parse_events, AddressSink};
+use crate::{
+    address_from_host, check_onion, is_url_path, shs_key, shs_key_string, unescape, AddressCount,
+    AddressType, Error, MultiserverAddress, NoPortString, PathInvalid, PortNotNumeric,
+    SecurityLayer, Transport, TransportLayerCount,
+};
+use snafu::{ensure, OptionExt, ResultExt};
+use ssb_multiformats::multikey::Multikey;
+use std::fmt;
+use std::str::FromStr;
+
+/// One `name:data` layer of an address.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// A `net`, `udp`, `http`, `https`, `ws`, `wss`, `onion` or `unix`
+    /// layer.
+    Transport {
+        transport: Transport,
+        address: AddressType,
+        port: u16,
+        /// Request path, for transports that allow one.
+        path: Option<String>,
+    },
+    Shs(Multikey),
+    Noauth,
+    /// Any layer this crate has no type for, kept verbatim.
+    Unknown {
+        name: String,
+        data: String,
+    },
+}
+
+/// A single address as the ordered stack of layers it is made of. Unlike
+/// `MultiserverAddress`, parsing succeeds for protocols this crate does not
+/// know, and formatting reproduces them unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProtocolStack(pub Vec<Protocol>);
+
+#[derive(Default)]
+struct Layers {
+    addresses: usize,
+    layers: Vec<(String, String)>,
+}
+
+impl AddressSink for Layers {
+    fn begin_address(&mut self) {
+        self.addresses += 1;
+    }
+
+    fn segment(&mut self, name: &str, data: &str) {
+        self.layers.push((name.to_string(), data.to_string()));
+    }
+}
+
+fn host_port(transport: &Transport, data: &str) -> Result<(AddressType, u16), Error> {
+    match data.rfind(':') {
+        Some(i) if !data.ends_with(']') => {
+            let port = u16::from_str(&data[i + 1..]).context(PortNotNumeric)?;
+            Ok((address_from_host(&data[..i])?, port))
+        }
+        _ => {
+            let port = transport.default_port().context(NoPortString)?;
+            Ok((address_from_host(data)?, port))
+        }
+    }
+}
+
+fn protocol(name: String, data: String) -> Result<Protocol, Error> {
+    let transport = match name.as_str() {
+        "net" => Transport::Net,
+        "udp" => Transport::Udp,
+        "http" => Transport::Http,
+        "https" => Transport::Https,
+        "ws" => Transport::Ws,
+        "wss" => Transport::Wss,
+        "onion" => Transport::Onion,
+        "unix" => {
+            return Ok(Protocol::Transport {
+                transport: Transport::Unix,
+                address: AddressType::SocketFilePath(unescape(&data)),
+                port: 0,
+                path: None,
+            })
+        }
+        "shs" => {
+            return Ok(Protocol::Shs(shs_key(&data)?));
+        }
+        "noauth" if data.is_empty() => return Ok(Protocol::Noauth),
+        _ => return Ok(Protocol::Unknown { name, data }),
+    };
+    let (host_port_data, path) = match data.find('/') {
+        Some(i) if transport.allows_path() => (&data[..i], Some(data[i..].to_string())),
+        _ => (data.as_str(), None),
+    };
+    ensure!(path.as_deref().is_none_or(is_url_path), PathInvalid);
+    let (address, port) = host_port(&transport, host_port_data)?;
+    check_onion(&transport, &address)?;
+    Ok(Protocol::Transport {
+        transport,
+        address,
+        port,
+        path,
+    })
+}
+
+impl FromStr for ProtocolStack {
+    type Err = Error;
+
+    fn from_str(st: &str) -> Result<ProtocolStack, Error> {
+        let mut layers = Layers::default();
+        parse_events(st, &mut layers)?;
+        ensure!(
+            layers.addresses == 1,
+            AddressCount {
+                count: layers.addresses
+            }
+        );
+        let protocols = layers
+            .layers
+            .into_iter()
+            .map(|(name, data)| protocol(name, data))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Transports this crate has no type for are `Unknown`, so a stack
+        // may have no transport layer, but never more than one.
+        let count = protocols
+            .iter()
+            .filter(|protocol| matches!(protocol, Protocol::Transport { .. }))
+            .count();
+        ensure!(count <= 1, TransportLayerCount { count });
+        Ok(ProtocolStack(protocols))
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Protocol::Transport {
+                transport,
+                address,
+                port,
+                path,
+            } => MultiserverAddress {
+                transport: transport.clone(),
+                address: address.clone(),
+                port: *port,
+                path: path.clone(),
+                security: Vec::new(),
+            }
+            .write_to(f),
+            Protocol::Shs(pub_key) => write!(f, "shs:{}", shs_key_string(pub_key)),
+            Protocol::Noauth => f.write_str("noauth"),
+            Protocol::Unknown { name, data } if data.is_empty() => f.write_str(name),
+            Protocol::Unknown { name, data } => write!(f, "{}:{}", name, data),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, protocol) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("~")?;
+            }
+            write!(f, "{}", protocol)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&MultiserverAddress> for ProtocolStack {
+    fn from(address: &MultiserverAddress) -> ProtocolStack {
+        let mut st = String::new();
+        address.write_to(&mut st).unwrap();
+        let (transport, _) = st.split_at(st.find('~').unwrap_or(st.len()));
+        let first = match address.transport {
+            Transport::Net
+            | Transport::Udp
+            | Transport::Http
+            | Transport::Https
+            | Transport::Ws
+            | Transport::Wss
+            | Transport::Onion
+            | Transport::Unix => Protocol::Transport {
+                transport: address.transport.clone(),
+                address: address.address.clone(),
+                port: address.port,
+                path: address.path.clone(),
+            },
+            _ => {
+                let i = transport.find(':').unwrap_or(transport.len());
+                Protocol::Unknown {
+                    name: transport[..i].to_string(),
+                    data: transport.get(i + 1..).unwrap_or_default().to_string(),
+                }
+            }
+        };
+        let security = address.security_layers().iter().map(|layer| match layer {
+            SecurityLayer::Shs(pub_key) => Protocol::Shs(pub_key.clone()),
+            SecurityLayer::Noauth => Protocol::Noauth,
+        });
+        ProtocolStack(std::iter::once(first).chain(security).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol_stack_unknown_ok() {
+        let input =
+            "net:host.com:8008~foo:bar:baz~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let stack = ProtocolStack::from_str(input).unwrap();
+        assert_eq!(stack.0.len(), 3);
+        assert_eq!(
+            stack.0[1],
+            Protocol::Unknown {
+                name: "foo".to_string(),
+                data: "bar:baz".to_string()
+            }
+        );
+        match &stack.0[0] {
+            Protocol::Transport {
+                transport, port, ..
+            } => assert_eq!((transport, *port), (&Transport::Net, 8008)),
+            other => panic!("unexpected layer: {:?}", other),
+        }
+        assert_eq!(stack.to_string(), input);
+    }
+    #[test]
+    fn protocol_stack_from_address_ok() {
+        let input = "https:room.example.com/alias~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = MultiserverAddress::from_str(input).unwrap();
+        let stack = ProtocolStack::from(&address);
+        match &stack.0[0] {
+            Protocol::Transport {
+                transport, path, ..
+            } => assert_eq!(
+                (transport, path.as_deref()),
+                (&Transport::Https, Some("/alias"))
+            ),
+            other => panic!("unexpected layer: {:?}", other),
+        }
+        assert_eq!(stack.to_string(), input);
+        assert_eq!(ProtocolStack::from_str(input).unwrap(), stack);
+    }
+    #[test]
+    fn protocol_stack_validation_err() {
+        assert!(matches!(
+            ProtocolStack::from_str("onion:foo.com:80~noauth"),
+            Err(Error::OnionInvalid { .. })
+        ));
+        let many = format!("net:host.com:8008{}", "~noauth".repeat(crate::MAX_SEGMENTS));
+        assert!(matches!(
+            ProtocolStack::from_str(&many),
+            Err(Error::TooManySegments { .. })
+        ));
+        assert!(matches!(
+            ProtocolStack::from_str("net:a.com:1~ws:b.com:2"),
+            Err(Error::TransportLayerCount { count: 2 })
+        ));
+        assert!(matches!(
+            ProtocolStack::from_str("net:a.com:1;net:b.com:2"),
+            Err(Error::AddressCount { count: 2 })
+        ));
+    }
+}
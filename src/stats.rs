@@ -0,0 +1,92 @@
+use crate::{AddressType, MultiserverAddress};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub total: usize,
+    pub protocols: BTreeMap<&'static str, usize>,
+    pub ports: BTreeMap<u16, usize>,
+    pub tlds: BTreeMap<String, usize>,
+    pub onion: usize,
+    pub clearnet: usize,
+    pub distinct_keys: usize,
+    /// Keys advertised by more than one address in the list.
+    pub duplicated_keys: usize,
+}
+
+impl Summary {
+    pub fn onion_ratio(&self) -> f64 {
+        match self.onion + self.clearnet {
+            0 => 0.0,
+            total => self.onion as f64 / total as f64,
+        }
+    }
+}
+
+pub fn stats(addresses: &[MultiserverAddress]) -> Summary {
+    let mut summary = Summary {
+        total: addresses.len(),
+        ..Summary::default()
+    };
+    let mut keys: HashMap<String, usize> = HashMap::new();
+
+    for address in addresses {
+        *summary
+            .protocols
+            .entry(address.transport.as_str())
+            .or_insert(0) += 1;
+        *summary.ports.entry(address.port).or_insert(0) += 1;
+
+        match &address.address {
+            AddressType::Hostname(host) => {
+                let host = host.to_lowercase();
+                let tld = host.rsplit('.').next().unwrap_or_default().to_string();
+                if tld == "onion" {
+                    summary.onion += 1;
+                } else {
+                    summary.clearnet += 1;
+                }
+                *summary.tlds.entry(tld).or_insert(0) += 1;
+            }
+            AddressType::Ip(_) => summary.clearnet += 1,
+            _ => (),
+        }
+
+        if let Some(pub_key) = address.pub_key() {
+            *keys.entry(pub_key.to_legacy_string()).or_insert(0) += 1;
+        }
+    }
+
+    summary.distinct_keys = keys.len();
+    summary.duplicated_keys = keys.values().filter(|count| **count > 1).count();
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn stats_ok() {
+        let addresses: Vec<MultiserverAddress> = [
+            "net:192.168.178.17:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+            "net:3wmnbu4vxpgzfvhn.onion:8009~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        ]
+        .iter()
+        .map(|s| MultiserverAddress::from_str(s).unwrap())
+        .collect();
+
+        let summary = stats(&addresses);
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.protocols["net"], 3);
+        assert_eq!(summary.ports[&8008], 2);
+        assert_eq!(summary.tlds["com"], 1);
+        assert_eq!(summary.tlds["onion"], 1);
+        assert_eq!(summary.onion, 1);
+        assert_eq!(summary.clearnet, 2);
+        assert_eq!(summary.distinct_keys, 1);
+        assert_eq!(summary.duplicated_keys, 1);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{pub_key_base64, Error, MultiserverAddress};
+use ssb_multiformats::multikey::Multikey;
+use std::str::FromStr;
+
+/// An address string with `{n}` and `{key}` placeholders, such as
+/// `net:10.0.0.{n}:8008~shs:{key}`, for generating fleets of addresses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AddressTemplate {
+    template: String,
+    uses_key: bool,
+}
+
+impl AddressTemplate {
+    pub fn parse(template: &str) -> Result<AddressTemplate, Error> {
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').ok_or(Error::TemplateInvalid {})?;
+            match &rest[start..start + end + 1] {
+                "{n}" | "{key}" => (),
+                _ => return Err(Error::TemplateInvalid {}),
+            }
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(Error::TemplateInvalid {});
+        }
+
+        Ok(AddressTemplate {
+            template: template.to_string(),
+            uses_key: template.contains("{key}"),
+        })
+    }
+
+    /// Produces one address per value of `range`. When the template uses
+    /// `{key}`, the i-th value is paired with the i-th key and expansion stops
+    /// when either runs out.
+    pub fn expand<I>(&self, range: I, keys: &[Multikey]) -> Result<Vec<MultiserverAddress>, Error>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut keys = keys.iter();
+        let mut addresses = Vec::new();
+
+        for n in range {
+            let mut address = self.template.replace("{n}", &n.to_string());
+            if self.uses_key {
+                match keys.next() {
+                    Some(key) => address = address.replace("{key}", &pub_key_base64(key)),
+                    None => break,
+                }
+            }
+            addresses.push(MultiserverAddress::from_str(&address)?);
+        }
+
+        Ok(addresses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AddressType;
+
+    #[test]
+    fn template_expand_ok() {
+        let key = Multikey::from_ed25519(&[7; 32]);
+        let template = AddressTemplate::parse("net:10.0.0.{n}:8008~shs:{key}").unwrap();
+        let addresses = template
+            .expand(1..=3, &[key.clone(), key.clone(), key.clone()])
+            .unwrap();
+
+        assert_eq!(addresses.len(), 3);
+        assert_eq!(addresses[2].pub_key(), Some(&key));
+        match addresses[2].address {
+            AddressType::Ip(ip) => assert_eq!(ip.to_string(), "10.0.0.3"),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn template_expand_short_keys_ok() {
+        let key = Multikey::from_ed25519(&[7; 32]);
+        let template = AddressTemplate::parse("net:10.0.0.1:{n}~shs:{key}").unwrap();
+        assert_eq!(template.expand(8000..8010, &[key]).unwrap().len(), 1);
+    }
+    #[test]
+    fn template_parse_err() {
+        assert!(AddressTemplate::parse("net:10.0.0.{m}:8008").is_err());
+        assert!(AddressTemplate::parse("net:10.0.0.{n:8008").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{Error, MultiserverAddress, TestnetTooLarge, Transport};
+use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use snafu::OptionExt;
+use ssb_multiformats::multikey::Multikey;
+use std::net::{Ipv4Addr, TcpListener};
+
+/// Nodes listen on consecutive loopback ports starting here, two per node.
+pub const BASE_PORT: u16 = 26000;
+
+pub struct TestNode {
+    pub keypair: Keypair,
+    pub addresses: Vec<MultiserverAddress>,
+}
+
+/// Derives `nodes` keypairs from `seed` and gives each node a loopback `net:`
+/// address and a `ws:` address on the port after it. The same seed always
+/// yields the same network. Fails if the ports would run past 65535.
+pub fn generate(nodes: u16, seed: u64) -> Result<Vec<TestNode>, Error> {
+    // The last port in use, checked once so the ports below cannot overflow.
+    nodes
+        .checked_mul(2)
+        .and_then(|ports| (BASE_PORT - 1).checked_add(ports))
+        .context(TestnetTooLarge { nodes })?;
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    Ok((0..nodes)
+        .map(|i| {
+            let net_port = BASE_PORT + 2 * i;
+            let mut secret = [0; 32];
+            rng.fill_bytes(&mut secret);
+            let secret = SecretKey::from_bytes(&secret).unwrap();
+            let public = PublicKey::from(&secret);
+            let pub_key = Multikey::from_ed25519(public.as_bytes());
+
+            let ws = MultiserverAddress {
+                transport: Transport::Ws,
+                ..MultiserverAddress::loopback(net_port + 1, pub_key.clone())
+            };
+
+            TestNode {
+                keypair: Keypair { secret, public },
+                addresses: vec![MultiserverAddress::loopback(net_port, pub_key), ws],
+            }
+        })
+        .collect())
+}
+
+/// A loopback port that was free when this was called, for pairing with
+/// `MultiserverAddress::loopback`. Another process may grab it before it is
+/// bound again.
+pub fn free_port() -> u16 {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .expect("no free loopback port")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_deterministic_ok() {
+        let a = generate(3, 42).unwrap();
+        let b = generate(3, 42).unwrap();
+        let c = generate(3, 43).unwrap();
+
+        assert_eq!(a.len(), 3);
+        assert_eq!(a[2].addresses, b[2].addresses);
+        assert_ne!(a[0].addresses, c[0].addresses);
+        assert_ne!(a[0].addresses[0].pub_key(), a[1].addresses[0].pub_key());
+        assert_eq!(a[1].addresses[0].port, BASE_PORT + 2);
+        assert_eq!(a[1].addresses[1].transport, Transport::Ws);
+        assert_eq!(a[1].addresses[1].port, BASE_PORT + 3);
+    }
+    #[test]
+    fn generate_too_large_err() {
+        match generate(u16::MAX, 42) {
+            Err(Error::TestnetTooLarge { nodes }) => assert_eq!(nodes, u16::MAX),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn free_port_ok() {
+        assert_ne!(free_port(), 0);
+    }
+}
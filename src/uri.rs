@@ -0,0 +1,75 @@
+use crate::{Error, MultiserverAddress, SsbUriInvalid};
+use snafu::{ensure, OptionExt};
+use std::str::FromStr;
+use url::{form_urlencoded, Url};
+
+impl MultiserverAddress {
+    /// The experimental SSB URI asking a client to add this address as a pub,
+    /// `ssb:experimental?action=add-pub&msaddr=<percent-encoded address>`.
+    pub fn to_ssb_uri(&self) -> String {
+        // Spaces are written as `%20` rather than `+`, which `from_ssb_uri`
+        // reads as a literal `+`.
+        let msaddr = form_urlencoded::byte_serialize(self.to_string().as_bytes())
+            .collect::<String>()
+            .replace('+', "%20");
+        format!("ssb:experimental?action=add-pub&msaddr={}", msaddr)
+    }
+
+    /// The address in the `msaddr` parameter of an experimental SSB URI, as
+    /// found in deep links from Manyverse and Planetary.
+    pub fn from_ssb_uri(uri: &str) -> Result<MultiserverAddress, Error> {
+        let url = Url::parse(uri).ok().context(SsbUriInvalid)?;
+        ensure!(
+            url.scheme() == "ssb" && url.path() == "experimental",
+            SsbUriInvalid
+        );
+        // Some clients leave `+` in base64 keys unencoded, which the query
+        // decoder would read as a space, so a raw `+` is kept as is and only
+        // encoded spaces are decoded to spaces.
+        let query = url.query().unwrap_or("").replace('+', "%2B");
+        let msaddr = form_urlencoded::parse(query.as_bytes())
+            .find(|(name, _)| name == "msaddr")
+            .context(SsbUriInvalid)?
+            .1;
+        MultiserverAddress::from_str(&msaddr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MultiserverAddress;
+    use std::str::FromStr;
+
+    #[test]
+    fn ssb_uri_round_trip_ok() {
+        let address = MultiserverAddress::from_str(
+            "net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=",
+        )
+        .unwrap();
+        let uri = address.to_ssb_uri();
+        assert_eq!(
+            uri,
+            "ssb:experimental?action=add-pub&msaddr=net%3Ahost.com%3A8008%7Eshs%3AHDOUC17%2FnBPzbVjT3%2BnUsLf%2F4p9lyIChEzMAxrHJQo4%3D"
+        );
+        assert_eq!(MultiserverAddress::from_ssb_uri(&uri).unwrap(), address);
+        assert_eq!(
+            MultiserverAddress::from_ssb_uri(
+                "ssb:experimental?action=add-pub&msaddr=net:host.com:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4="
+            )
+            .unwrap(),
+            address
+        );
+
+        let socket = MultiserverAddress::from_str("unix:/tmp/my sock~noauth").unwrap();
+        let uri = socket.to_ssb_uri();
+        assert!(uri.contains("my%20sock"));
+        assert_eq!(MultiserverAddress::from_ssb_uri(&uri).unwrap(), socket);
+    }
+
+    #[test]
+    fn ssb_uri_err() {
+        assert!(MultiserverAddress::from_ssb_uri("ssb:experimental?action=add-pub").is_err());
+        assert!(MultiserverAddress::from_ssb_uri("https://host.com/?msaddr=net:a:1").is_err());
+        assert!(MultiserverAddress::from_ssb_uri("not a uri").is_err());
+    }
+}
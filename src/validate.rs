@@ -0,0 +1,164 @@
+use crate::{check_limits, is_onion_v3, is_url_path, Error, MultiserverAddress, ADDRESS_RE};
+use regex::CaptureLocations;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+// Group indices by name, for reading `CaptureLocations`.
+static GROUPS: LazyLock<HashMap<&'static str, usize>> = LazyLock::new(|| {
+    ADDRESS_RE
+        .capture_names()
+        .enumerate()
+        .filter_map(|(i, name)| name.map(|name| (name, i)))
+        .collect()
+});
+
+thread_local! {
+    // Capture slots reused by every check on the thread, so only the first
+    // check allocates them.
+    static LOCATIONS: RefCell<CaptureLocations> = RefCell::new(ADDRESS_RE.capture_locations());
+}
+
+impl MultiserverAddress {
+    /// Whether `st` parses as an address. Common `net`, `ws` and similar
+    /// addresses are checked without allocating; anything unusual falls back
+    /// to a full parse, so the answer always agrees with `from_str`.
+    pub fn is_valid(st: &str) -> bool {
+        is_plainly_valid(st) || MultiserverAddress::from_str(st).is_ok()
+    }
+
+    /// Like `is_valid`, but with the error `from_str` would return.
+    pub fn validate(st: &str) -> Result<(), Error> {
+        if is_plainly_valid(st) {
+            return Ok(());
+        }
+        MultiserverAddress::from_str(st).map(|_| ())
+    }
+}
+
+// True only for input `from_str` certainly accepts. A false result means
+// "ask the parser", not "invalid".
+fn is_plainly_valid(st: &str) -> bool {
+    if check_limits(st).is_err() {
+        return false;
+    }
+    LOCATIONS.with(|locations| {
+        let mut locations = locations.borrow_mut();
+        ADDRESS_RE.captures_read(&mut locations, st).is_some()
+            && groups_plainly_valid(|name| {
+                locations
+                    .get(GROUPS[name])
+                    .map(|(start, end)| &st[start..end])
+            })
+    })
+}
+
+// The checks of `is_plainly_valid` on the matched groups, looked up by name.
+fn groups_plainly_valid<'a>(text: impl Fn(&str) -> Option<&'a str>) -> bool {
+    if let Some(key) = text("pub_key") {
+        if !is_plain_key(key) {
+            return false;
+        }
+    }
+    let transport = match text("transport") {
+        Some(transport) => transport,
+        // Tunnel keys need decoding and web hosts without a port are left
+        // to the parser; the other portless forms are fully described by
+        // the regex.
+        None => return text("portal").is_none() && text("web").is_none(),
+    };
+    let allows_path = matches!(transport, "http" | "https" | "ws" | "wss");
+    let port_ok = matches!(text("port").map(u16::from_str), Some(Ok(_)));
+    if (transport == "quic" && !cfg!(feature = "quic"))
+        || (text("path").is_some() && !allows_path)
+        || text("path").is_some_and(|path| !is_url_path(path))
+        || (text("cert_hash").is_some() && transport != "quic")
+        || !port_ok
+    {
+        return false;
+    }
+    let ip = text("ipv4")
+        .or_else(|| text("ipv6"))
+        .or_else(|| text("ipv6_bracketed"));
+    match (ip, text("url")) {
+        (Some(ip), None) => transport != "onion" && IpAddr::from_str(ip).is_ok(),
+        (None, Some(host)) if transport == "onion" => is_onion_v3(host),
+        (None, Some(host)) => is_plain_hostname(host),
+        _ => false,
+    }
+}
+
+// A bare or `.ed25519`-suffixed base64 encoding of exactly 32 bytes.
+fn is_plain_key(key: &str) -> bool {
+    let key = key.strip_prefix('@').unwrap_or(key);
+    let key = key.strip_suffix(".ed25519").unwrap_or(key);
+    let mut bytes = [0; 33];
+    key.len() == 44 && base64::decode_config_slice(key, base64::STANDARD, &mut bytes) == Ok(32)
+}
+
+// Lowercase letter-digit-hyphen names, which the URL host parser keeps as
+// they are. Names it might rewrite (uppercase, punycode, numeric last
+// labels that read as IPv4) are left to the parser.
+fn is_plain_hostname(host: &str) -> bool {
+    let last = host.rsplit('.').next().unwrap_or_default();
+    last.starts_with(|c: char| c.is_ascii_lowercase())
+        && !host.starts_with("xn--")
+        && !host.contains(".xn--")
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_plainly_valid;
+    use crate::MultiserverAddress;
+    use std::str::FromStr;
+
+    #[test]
+    fn validate_agrees_with_parse_ok() {
+        let key = "HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let inputs = [
+            format!("net:host.com:8008~shs:{}", key),
+            format!("net:host.com:8008~shs:{}.ed25519", key),
+            format!("net:host.com:8008~shs:@{}.ed25519", key),
+            format!("net:Host.com:8008~shs:{}", key),
+            format!("net:host.com:8008~shs:{}", &key[1..]),
+            format!("net:host.com:80080~shs:{}", key),
+            format!("net:1.2.3.4:8008~shs:{}", key),
+            format!("net:1.2.3.400:8008~shs:{}", key),
+            format!("net:[fe80::1]:8008~shs:{}", key),
+            format!("net:0x7f.1:8008~shs:{}", key),
+            format!("ws:host.com:80/path~shs:{}", key),
+            format!("https:host.com:443/path~shs:{}", key),
+            format!("wss:host.com/path~shs:{}", key),
+            format!("wss:[fe80::1]~shs:{}", key),
+            format!("wss:host.com:~shs:{}", key),
+            "onion:host.com:80~noauth".to_string(),
+            "net:bücher.example:8008~noauth".to_string(),
+            "unix:/tmp/ssb.sock~noauth".to_string(),
+            "tunnel:@abc:@def~noauth".to_string(),
+            "net:host.com".to_string(),
+            "".to_string(),
+        ];
+        for st in &inputs {
+            let parsed = MultiserverAddress::from_str(st);
+            assert_eq!(MultiserverAddress::is_valid(st), parsed.is_ok(), "{}", st);
+            assert_eq!(
+                MultiserverAddress::validate(st).map_err(|err| err.to_string()),
+                parsed.map(|_| ()).map_err(|err| err.to_string()),
+            );
+        }
+        for st in &inputs[..3] {
+            assert!(is_plainly_valid(st), "{}", st);
+        }
+        assert!(is_plainly_valid(&inputs[6]) && is_plainly_valid(&inputs[8]));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::MultiserverAddress;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// A parsed address as seen from JavaScript, with read-only fields.
+#[wasm_bindgen]
+pub struct Address(MultiserverAddress);
+
+#[wasm_bindgen]
+impl Address {
+    #[wasm_bindgen(getter)]
+    pub fn transport(&self) -> String {
+        self.0.transport.as_str().to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn host(&self) -> String {
+        self.0.host_text()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn port(&self) -> u16 {
+        self.0.port
+    }
+
+    /// The `@…=.ed25519` feed id of the `shs` key.
+    #[wasm_bindgen(getter)]
+    pub fn key(&self) -> Option<String> {
+        self.0.feed_id()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn path(&self) -> Option<String> {
+        self.0.path.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn noauth(&self) -> bool {
+        self.0.is_noauth()
+    }
+}
+
+/// Parses an address, throwing the error message on failure.
+#[wasm_bindgen]
+pub fn parse(st: &str) -> Result<Address, JsValue> {
+    MultiserverAddress::from_str(st)
+        .map(Address)
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn format(address: &Address) -> String {
+    address.0.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only the success path is exercised: building a `JsValue` requires a
+    // wasm host.
+    #[test]
+    fn wasm_parse_ok() {
+        let st = "net:[::1]:8008~shs:HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=";
+        let address = parse(st).ok().unwrap();
+        assert_eq!(address.transport(), "net");
+        assert_eq!(address.host(), "::1");
+        assert_eq!(address.port(), 8008);
+        assert_eq!(
+            address.key().unwrap(),
+            "@HDOUC17/nBPzbVjT3+nUsLf/4p9lyIChEzMAxrHJQo4=.ed25519"
+        );
+        assert!(!address.noauth());
+        assert_eq!(format(&address), st);
+    }
+}